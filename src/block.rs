@@ -0,0 +1,125 @@
+//! ## Block
+//!
+//! Parsing of the IEEE 488.2 arbitrary block data returned by waveform, screenshot and
+//! trace queries.
+//!
+//! A definite length block is `#<n><length><data>`, where `<n>` is a single non-zero digit
+//! giving the number of digits of `<length>`. An indefinite length block is `#0<data>`,
+//! ended by a newline sent with the end of the message.
+//!
+
+use crate::error::{Error, Result};
+
+/// The length of the data of a block
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockLength {
+    /// The data is `usize` bytes long
+    Definite(usize),
+    /// The data runs until the newline ending the message
+    Indefinite,
+}
+
+/// ### Parse Header
+///
+/// Parse the header at the start of `data`, skipping leading whitespace.
+///
+/// Return the size of the header and the length of the block, or `None` if `data` is too
+/// short to hold the whole header.
+///
+pub fn parse_header(data: &[u8]) -> Result<Option<(usize, BlockLength)>> {
+    // find the start of the block
+    let start = match data.iter().position(|v| !v.is_ascii_whitespace()) {
+        Some(start) => start,
+        None => return Ok(None),
+    };
+    if data[start] != b'#' {
+        return Err(Error::InvalidBlockHeader);
+    }
+
+    // get the number of digits of the length
+    let num_digits = match data.get(start + 1) {
+        Some(digit) if digit.is_ascii_digit() => (digit - b'0') as usize,
+        Some(_) => return Err(Error::InvalidBlockHeader),
+        None => return Ok(None),
+    };
+    if num_digits == 0 {
+        return Ok(Some((start + 2, BlockLength::Indefinite)));
+    }
+
+    // get the length
+    let header_size = start + 2 + num_digits;
+    let digits = match data.get(start + 2..header_size) {
+        Some(digits) => digits,
+        None => return Ok(None),
+    };
+    if !digits.iter().all(u8::is_ascii_digit) {
+        return Err(Error::InvalidBlockHeader);
+    }
+    let length: usize = std::str::from_utf8(digits)?
+        .parse()
+        .map_err(|_| Error::InvalidBlockHeader)?;
+
+    Ok(Some((header_size, BlockLength::Definite(length))))
+}
+
+/// ### Byte Order
+///
+/// The order of the bytes of the values in a block, as set with `:FORMat:BORDer`.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// Most significant byte first (`:FORM:BORD NORM`)
+    BigEndian,
+    /// Least significant byte first (`:FORM:BORD SWAP`)
+    LittleEndian,
+}
+
+/// ### Binary Value
+///
+/// A number which can be decoded from the data of a block.
+///
+pub trait BinaryValue: Sized {
+    /// The size in bytes of a value
+    const SIZE: usize;
+
+    /// Decode a value from `SIZE` bytes
+    fn from_bytes(bytes: &[u8], byte_order: ByteOrder) -> Self;
+}
+
+macro_rules! impl_binary_value {
+    ($($t:ty),*) => {
+        $(
+            impl BinaryValue for $t {
+                const SIZE: usize = std::mem::size_of::<$t>();
+
+                fn from_bytes(bytes: &[u8], byte_order: ByteOrder) -> Self {
+                    let bytes: [u8; std::mem::size_of::<$t>()] = bytes.try_into().unwrap();
+                    match byte_order {
+                        ByteOrder::BigEndian => <$t>::from_be_bytes(bytes),
+                        ByteOrder::LittleEndian => <$t>::from_le_bytes(bytes),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_binary_value!(i8, u8, i16, u16, i32, u32, i64, u64, f32, f64);
+
+/// ### Decode
+///
+/// Decode the data of a block into values.
+///
+pub fn decode<T: BinaryValue>(data: &[u8], byte_order: ByteOrder) -> Result<Vec<T>> {
+    if !data.len().is_multiple_of(T::SIZE) {
+        return Err(Error::InvalidBlockLength {
+            length: data.len(),
+            element_size: T::SIZE,
+        });
+    }
+
+    Ok(data
+        .chunks_exact(T::SIZE)
+        .map(|bytes| T::from_bytes(bytes, byte_order))
+        .collect())
+}
@@ -3,21 +3,32 @@
 //! Low level functions to read and write data to the bulk endpoints.
 //!
 
+use crate::communication::control;
 use crate::constants::{bulk_msg_id, misc};
-use crate::error::Error;
-use crate::types::{BTag, Capabilities, Endpoint, Handle, Timeout};
+use crate::error::{Error, Result};
+use crate::types::{BTag, Endpoint, Handle, ReadEnd, Response, Timeout};
+
+use std::io::Read;
 
-use anyhow::Result;
 use rusb::{Direction, TransferType};
 
 /// ### Write
 ///
 /// Write data to the BULK OUT endpoint.
 ///
+/// The data is split in messages of at most `message_size` bytes. The last one ends with the
+/// end of message flag if `end_of_message` is set. Nothing is sent if `data` is empty and
+/// `end_of_message` is unset.
+///
+/// USBTMC forbids a transfer size of 0, so a message can't be ended with empty data:
+/// [`Error::EmptyMessage`] is returned instead.
+///
 pub fn write(
     handle: &Handle,
     btag: &BTag,
-    data: Vec<u8>,
+    data: &[u8],
+    end_of_message: bool,
+    message_size: u32,
     bulk_out_endpoint: &Endpoint,
     timeout: &Timeout,
 ) -> Result<()> {
@@ -25,132 +36,562 @@ pub fn write(
     if bulk_out_endpoint.direction != Direction::Out
         || bulk_out_endpoint.transfer_type != TransferType::Bulk
     {
-        return Err(Error::IncorrectEndpoint.into());
+        return Err(Error::IncorrectEndpoint);
+    }
+    if data.is_empty() && end_of_message {
+        return Err(Error::EmptyMessage);
     }
 
-    // count the number of transactions to do
-    let num_transactions: usize = match data.len() % misc::APPLICATION_BUFFER_SIZE as usize {
-        // the data is exactly sized to the application buffer size
-        0 => data.len() / misc::APPLICATION_BUFFER_SIZE as usize,
-        // the last transaction will be less big than the application buffer size (which is fine)
-        _ => data.len() / misc::APPLICATION_BUFFER_SIZE as usize + 1,
-    };
+    // count the number of messages to send
+    let message_size = message_size.max(1) as usize;
+    let num_messages = data.len().div_ceil(message_size);
 
-    // Seperate the data into transactions to send
-    for (transaction_number, transaction) in data
-        .chunks(misc::APPLICATION_BUFFER_SIZE as usize)
-        .enumerate()
-    {
-        // setup the header
+    for (message_number, payload) in data.chunks(message_size).enumerate() {
         let header = device_dependent_msg_out_header(
             btag.get(),
-            transaction.len() as u32,
-            transaction_number + 1 == num_transactions,
+            payload.len() as u32,
+            end_of_message && message_number + 1 == num_messages,
         )?;
+        write_transfer(handle, header, payload, bulk_out_endpoint, timeout)?;
+    }
 
-        // setup a vector with the header and the transaction data
-        let mut data = Vec::from(header);
-        data.append(Vec::from(transaction).as_mut());
-
-        // send the transaction in transfers
-        for transfer in data.chunks(bulk_out_endpoint.max_packet_size as usize) {
-            // add padding to the transfer if required
-            let mut transfer = Vec::from(transfer);
-            let padding = transfer.len() % 4;
-            for _ in 0..padding {
-                transfer.push(0x00)
-            }
+    Ok(())
+}
 
-            // execute the transfer
-            handle.borrow().write_bulk(
-                bulk_out_endpoint.address,
-                &transfer,
-                timeout.borrow().clone(),
-            )?;
-        }
+/// ### Write From
+///
+/// Write `len` bytes read from `reader` to the BULK OUT endpoint.
+///
+/// The data is read and sent in messages of at most `message_size` bytes, so only one
+/// message is held in memory at a time. The last message ends with the end of message flag.
+///
+pub fn write_from(
+    handle: &Handle,
+    btag: &BTag,
+    mut reader: impl Read,
+    len: u64,
+    message_size: u32,
+    bulk_out_endpoint: &Endpoint,
+    timeout: &Timeout,
+) -> Result<()> {
+    // verify the endpoint is correct
+    if bulk_out_endpoint.direction != Direction::Out
+        || bulk_out_endpoint.transfer_type != TransferType::Bulk
+    {
+        return Err(Error::IncorrectEndpoint);
+    }
+
+    let mut remaining = len;
+    let mut payload: Vec<u8> = vec![0x00; (message_size.max(1) as u64).min(len) as usize];
+
+    while remaining > 0 {
+        // read the next message
+        let payload_size = (payload.len() as u64).min(remaining) as usize;
+        reader.read_exact(&mut payload[..payload_size])?;
+        remaining -= payload_size as u64;
+
+        let header =
+            device_dependent_msg_out_header(btag.get(), payload_size as u32, remaining == 0)?;
+        write_transfer(
+            handle,
+            header,
+            &payload[..payload_size],
+            bulk_out_endpoint,
+            timeout,
+        )?;
     }
 
     Ok(())
 }
 
-pub fn read(
+/// ### Vendor Write
+///
+/// Write vendor specific data to the BULK OUT endpoint.
+///
+/// The data is split in messages of at most `message_size` bytes.
+///
+pub fn vendor_write(
     handle: &Handle,
     btag: &BTag,
-    bulk_in_endpoint: &Endpoint,
+    data: &[u8],
+    message_size: u32,
     bulk_out_endpoint: &Endpoint,
-    device_capabilities: &Capabilities,
     timeout: &Timeout,
-) -> Result<Vec<u8>> {
-    // SETUP
-    // ==========
-
-    // verify the endpoints
+) -> Result<()> {
+    // verify the endpoint is correct
     if bulk_out_endpoint.direction != Direction::Out
         || bulk_out_endpoint.transfer_type != TransferType::Bulk
     {
-        return Err(Error::IncorrectEndpoint.into());
+        return Err(Error::IncorrectEndpoint);
     }
-    if bulk_in_endpoint.direction != Direction::In
+
+    for payload in data.chunks(message_size.max(1) as usize) {
+        let header = vendor_specific_out_header(btag.get(), payload.len() as u32)?;
+        write_transfer(handle, header, payload, bulk_out_endpoint, timeout)?;
+    }
+
+    Ok(())
+}
+
+/// Send a message in a single transfer
+fn write_transfer(
+    handle: &Handle,
+    header: [u8; 12],
+    payload: &[u8],
+    bulk_out_endpoint: &Endpoint,
+    timeout: &Timeout,
+) -> Result<()> {
+    // setup the transfer with the header and the payload.
+    // According to USBTMC spec, null bytes are added after the payload to make the total
+    // size of the transfer divisible by 4.
+    let transfer_size = (misc::USBTMC_HEADER_SIZE + payload.len()).next_multiple_of(4);
+    let mut transfer: Vec<u8> = Vec::with_capacity(transfer_size);
+    transfer.extend_from_slice(&header);
+    transfer.extend_from_slice(payload);
+    transfer.resize(transfer_size, 0x00);
+
+    // execute the transfer, libusb splits it in packets
+    handle
+        .borrow()
+        .write_bulk(bulk_out_endpoint.address, &transfer, *timeout.borrow())?;
+
+    Ok(())
+}
+
+/// ### Trigger
+///
+/// Send the USB488 TRIGGER message to the BULK OUT endpoint.
+///
+pub fn trigger(
+    handle: &Handle,
+    btag: &BTag,
+    bulk_out_endpoint: &Endpoint,
+    timeout: &Timeout,
+) -> Result<()> {
+    // verify the endpoint is correct
+    if bulk_out_endpoint.direction != Direction::Out
         || bulk_out_endpoint.transfer_type != TransferType::Bulk
     {
-        return Err(Error::IncorrectEndpoint.into());
+        return Err(Error::IncorrectEndpoint);
     }
 
-    // setup the header for the request
-    let term_char = match device_capabilities.supports_bulk_in_term_char {
-        true => Some(misc::DEFAULT_TERM_CHAR),
-        false => None,
-    };
-    let request_header = request_device_dependent_msg_in_header(
-        btag.get(),
-        bulk_in_endpoint.max_packet_size as u32,
-        term_char,
-    )?;
+    // the message is only a header
+    let header = trigger_header(btag.get())?;
+    handle
+        .borrow()
+        .write_bulk(bulk_out_endpoint.address, &header, *timeout.borrow())?;
+
+    Ok(())
+}
+
+/// ### Read
+///
+/// Read a device dependent message from the BULK IN endpoint.
+///
+/// Data is requested until the end of the message, until the termination character when
+/// `term_char` is set, or until `max_size` bytes were read. Each request asks the device for
+/// up to `transfer_size` bytes.
+///
+/// The termination character must only be set when the device supports it.
+///
+#[allow(clippy::too_many_arguments)]
+pub fn read(
+    handle: &Handle,
+    btag: &BTag,
+    bulk_in_endpoint: &Endpoint,
+    bulk_out_endpoint: &Endpoint,
+    term_char: Option<u8>,
+    transfer_size: u32,
+    max_size: usize,
+    timeout: &Timeout,
+) -> Result<Response> {
+    verify_endpoints(bulk_in_endpoint, bulk_out_endpoint)?;
 
-    let mut end_of_message = false;
     let mut output_data: Vec<u8> = Vec::new();
+    let mut scratch: Vec<u8> = vec![0x00; bulk_in_endpoint.max_packet_size as usize];
+
+    // READING LOOP
+    // ==========
+
+    let end = loop {
+        if output_data.len() >= max_size {
+            break ReadEnd::TransferSize;
+        }
 
-    let mut buffer: Vec<u8> =
-        vec![0x00; bulk_in_endpoint.max_packet_size as usize + misc::USBTMC_HEADER_SIZE];
+        let len = output_data.len();
+        let requested_size = (transfer_size as usize).min(max_size - len);
+        let transfer = request_transfer(
+            handle,
+            btag,
+            bulk_in_endpoint,
+            bulk_out_endpoint,
+            InRequest::DeviceDependent(term_char),
+            requested_size,
+            &mut scratch,
+            timeout,
+        )?;
+
+        // only make room for the data the device announced
+        output_data.resize(len + transfer.transfer_size, 0x00);
+        let bytes_read = read_transfer(
+            handle,
+            bulk_in_endpoint,
+            &transfer,
+            &mut output_data[len..],
+            &mut scratch,
+            timeout,
+        )?;
+        output_data.truncate(len + bytes_read);
+        let attributes = transfer.attributes;
+
+        // check why the transfer ended
+        if let Some(end) = read_end(attributes, term_char) {
+            break end;
+        }
+    };
+
+    Ok(Response {
+        data: output_data,
+        end,
+    })
+}
+
+/// ### Read Into
+///
+/// Read a device dependent message from the BULK IN endpoint directly into `buf`.
+///
+/// Data is requested until the end of the message, until the termination character when
+/// `term_char` is set, or until `buf` is full. Each request asks the device for up to
+/// `transfer_size` bytes.
+///
+/// Return the number of bytes read and why the read ended.
+///
+#[allow(clippy::too_many_arguments)]
+pub fn read_into(
+    handle: &Handle,
+    btag: &BTag,
+    bulk_in_endpoint: &Endpoint,
+    bulk_out_endpoint: &Endpoint,
+    term_char: Option<u8>,
+    transfer_size: u32,
+    buf: &mut [u8],
+    timeout: &Timeout,
+) -> Result<(usize, ReadEnd)> {
+    verify_endpoints(bulk_in_endpoint, bulk_out_endpoint)?;
+
+    let mut len: usize = 0;
+    let mut scratch: Vec<u8> = vec![0x00; bulk_in_endpoint.max_packet_size as usize];
 
     // READING LOOP
     // ==========
 
-    while !end_of_message {
-        // execute the request
-        handle.borrow().write_bulk(
-            bulk_out_endpoint.address,
-            &request_header,
-            timeout.borrow().clone(),
+    let end = loop {
+        if len >= buf.len() {
+            break ReadEnd::TransferSize;
+        }
+        let requested_size = (transfer_size as usize).min(buf.len() - len);
+
+        let transfer = request_transfer(
+            handle,
+            btag,
+            bulk_in_endpoint,
+            bulk_out_endpoint,
+            InRequest::DeviceDependent(term_char),
+            requested_size,
+            &mut scratch,
+            timeout,
+        )?;
+        len += read_transfer(
+            handle,
+            bulk_in_endpoint,
+            &transfer,
+            &mut buf[len..len + requested_size],
+            &mut scratch,
+            timeout,
         )?;
+        let attributes = transfer.attributes;
+
+        // check why the transfer ended
+        if let Some(end) = read_end(attributes, term_char) {
+            break end;
+        }
+    };
+
+    Ok((len, end))
+}
+
+/// Verify the endpoints used to read a message
+fn verify_endpoints(bulk_in_endpoint: &Endpoint, bulk_out_endpoint: &Endpoint) -> Result<()> {
+    if bulk_out_endpoint.direction != Direction::Out
+        || bulk_out_endpoint.transfer_type != TransferType::Bulk
+    {
+        return Err(Error::IncorrectEndpoint);
+    }
+    if bulk_in_endpoint.direction != Direction::In
+        || bulk_in_endpoint.transfer_type != TransferType::Bulk
+    {
+        return Err(Error::IncorrectEndpoint);
+    }
 
-        // execute the read
+    Ok(())
+}
+
+/// Tell why a transfer ended from its bmTransferAttributes, if it ended the read
+fn read_end(attributes: u8, term_char: Option<u8>) -> Option<ReadEnd> {
+    if attributes & 0b0000_0001 != 0 {
+        return Some(ReadEnd::EndOfMessage);
+    }
+    if term_char.is_some() && attributes & 0b0000_0010 != 0 {
+        return Some(ReadEnd::TermChar);
+    }
+    None
+}
+
+/// The header of a BULK IN transfer, read with its first packet
+struct InTransfer {
+    /// The TransferSize announced by the device
+    transfer_size: usize,
+    /// The bmTransferAttributes of the transfer
+    attributes: u8,
+    /// The number of bytes of the first packet, kept in the scratch buffer
+    bytes_read: usize,
+}
+
+/// The kind of data requested from the BULK IN endpoint
+#[derive(Clone, Copy)]
+enum InRequest {
+    /// A device dependent message, stopping on the termination character if set
+    DeviceDependent(Option<u8>),
+    /// Vendor specific data
+    VendorSpecific,
+}
+
+/// Request a transfer of up to `requested_size` bytes and read its first packet, holding
+/// the header, into `scratch`, which must be one packet long.
+#[allow(clippy::too_many_arguments)]
+fn request_transfer(
+    handle: &Handle,
+    btag: &BTag,
+    bulk_in_endpoint: &Endpoint,
+    bulk_out_endpoint: &Endpoint,
+    request: InRequest,
+    requested_size: usize,
+    scratch: &mut [u8],
+    timeout: &Timeout,
+) -> Result<InTransfer> {
+    // execute the request
+    let request_btag = btag.get();
+    let (request_header, msg_id) = match request {
+        InRequest::DeviceDependent(term_char) => (
+            request_device_dependent_msg_in_header(request_btag, requested_size as u32, term_char)?,
+            bulk_msg_id::DEVICE_DEPENDENT_MSG_IN,
+        ),
+        InRequest::VendorSpecific => (
+            request_vendor_specific_in_header(request_btag, requested_size as u32)?,
+            bulk_msg_id::VENDOR_SPECIFIC_MSG_IN,
+        ),
+    };
+    handle.borrow().write_bulk(
+        bulk_out_endpoint.address,
+        &request_header,
+        *timeout.borrow(),
+    )?;
+
+    // read the first packet, holding the header
+    let bytes_read =
+        handle
+            .borrow()
+            .read_bulk(bulk_in_endpoint.address, scratch, *timeout.borrow())?;
+    let (transfer_size, attributes) =
+        parse_in_header(&scratch[..bytes_read], msg_id, request_btag, requested_size)?;
+
+    Ok(InTransfer {
+        transfer_size,
+        attributes,
+        bytes_read,
+    })
+}
+
+/// Read the data of a requested transfer into `buf`, which must hold the TransferSize.
+///
+/// The data of the first packet is taken from `scratch`. The following packets are read
+/// directly into `buf`, except for the last partial packet, which may hold alignment bytes
+/// not fitting in `buf`.
+///
+/// Return the number of bytes read.
+///
+fn read_transfer(
+    handle: &Handle,
+    bulk_in_endpoint: &Endpoint,
+    transfer: &InTransfer,
+    buf: &mut [u8],
+    scratch: &mut [u8],
+    timeout: &Timeout,
+) -> Result<usize> {
+    let packet_size = scratch.len();
+    let InTransfer {
+        transfer_size,
+        bytes_read,
+        ..
+    } = *transfer;
+
+    // According to USBTMC spec, null bytes are added to make the total size divisible by 4.
+    // The transfer size excludes these padding bytes.
+    let mut total_remaining = (misc::USBTMC_HEADER_SIZE + transfer_size)
+        .next_multiple_of(4)
+        .saturating_sub(bytes_read);
+
+    let mut len = (bytes_read - misc::USBTMC_HEADER_SIZE).min(transfer_size);
+    buf[..len].copy_from_slice(&scratch[misc::USBTMC_HEADER_SIZE..misc::USBTMC_HEADER_SIZE + len]);
+
+    // a short packet ends the transfer
+    let mut ended = bytes_read < packet_size;
+
+    // read the full packets directly into the buffer
+    let direct_size = total_remaining.min(buf.len() - len);
+    let direct_size = direct_size - direct_size % packet_size;
+    if !ended && direct_size > 0 {
         let bytes_read = handle.borrow().read_bulk(
             bulk_in_endpoint.address,
-            &mut buffer,
-            timeout.borrow().clone(),
+            &mut buf[len..len + direct_size],
+            *timeout.borrow(),
         )?;
+        total_remaining = total_remaining.saturating_sub(bytes_read);
+        len = (len + bytes_read).min(transfer_size);
+        ended = bytes_read < direct_size;
+    }
+
+    // read what is left of the transfer
+    while !ended && total_remaining > 0 {
+        let bytes_read =
+            handle
+                .borrow()
+                .read_bulk(bulk_in_endpoint.address, scratch, *timeout.borrow())?;
+        total_remaining = total_remaining.saturating_sub(bytes_read);
+
+        let n = bytes_read.min(transfer_size - len);
+        buf[len..len + n].copy_from_slice(&scratch[..n]);
+        len += n;
+        ended = bytes_read < packet_size;
+    }
 
-        // Add data to the total output
-        output_data.append(&mut buffer[misc::USBTMC_HEADER_SIZE..bytes_read].to_vec());
+    // the device must send every byte announced in the header
+    if len < transfer_size {
+        return Err(Error::BulkInShortTransfer {
+            received: len,
+            transfer_size,
+        });
+    }
+
+    Ok(len)
+}
 
-        // check if its the end of the message
-        let read_attributes = buffer[8];
-        end_of_message = read_attributes & 0b0000_0001 != 0;
+/// Validate the header of a BULK IN transfer answering the request identified by `btag`.
+///
+/// Return the TransferSize and bmTransferAttributes of the transfer.
+///
+fn parse_in_header(
+    transfer: &[u8],
+    msg_id: u8,
+    btag: u8,
+    requested_size: usize,
+) -> Result<(usize, u8)> {
+    if transfer.len() < misc::USBTMC_HEADER_SIZE {
+        return Err(Error::BulkInShortHeader);
+    }
+    if transfer[0] != msg_id {
+        return Err(Error::BulkInUnexpectedMsgId(transfer[0]));
+    }
+    if transfer[1] != btag || transfer[2] != !btag {
+        return Err(Error::BulkInMismatchedBTag {
+            expected: btag,
+            received: transfer[1],
+        });
     }
 
-    // According to USBTMC spec, null bytes must be added to make
-    // the total size divisible by 4. Null bytes are added as padding.
+    let transfer_size =
+        u32::from_le_bytes([transfer[4], transfer[5], transfer[6], transfer[7]]) as usize;
+    if transfer_size > requested_size {
+        return Err(Error::BulkInTransferSizeExceeded {
+            transfer_size,
+            requested: requested_size,
+        });
+    }
 
-    // get the position where null byte padding begins
-    let padding_start_pos = output_data[output_data.len()-4..]
-        .iter()
-        .rev()
-        .position(|v| *v != 0x00)
-        .unwrap_or(0);
-    let padding_start_pos = padding_start_pos + (output_data.len() - 4);
-    // remove the null bytes from the data
-    output_data.drain(padding_start_pos..);
+    Ok((transfer_size, transfer[8]))
+}
+
+/// ### Vendor Read
+///
+/// Read vendor specific data from the BULK IN endpoint.
+///
+/// Vendor specific messages have no end of message flag, so data is requested until the
+/// device sends less than what was requested. Each request asks the device for up to
+/// `transfer_size` bytes.
+///
+pub fn vendor_read(
+    handle: &Handle,
+    btag: &BTag,
+    bulk_in_endpoint: &Endpoint,
+    bulk_out_endpoint: &Endpoint,
+    transfer_size: u32,
+    timeout: &Timeout,
+) -> Result<Vec<u8>> {
+    verify_endpoints(bulk_in_endpoint, bulk_out_endpoint)?;
+
+    let requested_size = transfer_size.max(1) as usize;
+    let mut output_data: Vec<u8> = Vec::new();
+    let mut scratch: Vec<u8> = vec![0x00; bulk_in_endpoint.max_packet_size as usize];
+
+    loop {
+        let len = output_data.len();
+        let transfer = match request_transfer(
+            handle,
+            btag,
+            bulk_in_endpoint,
+            bulk_out_endpoint,
+            InRequest::VendorSpecific,
+            requested_size,
+            &mut scratch,
+            timeout,
+        ) {
+            Ok(transfer) => transfer,
+            // the data was a multiple of the requested size, and the device has nothing left
+            // to answer the last request with, which is aborted
+            Err(Error::Timeout) if len > 0 => {
+                match control::abort_bulk_in_transfer(
+                    handle,
+                    bulk_in_endpoint,
+                    btag.last(),
+                    timeout,
+                ) {
+                    Ok(_) => {}
+                    // the device had nothing left to send, only the halt is left to clear
+                    Err(Error::StatusFailure) => control::clear_feature(handle, bulk_in_endpoint)?,
+                    Err(e) => return Err(e),
+                }
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+
+        // only make room for the data the device announced
+        output_data.resize(len + transfer.transfer_size, 0x00);
+        let bytes_read = read_transfer(
+            handle,
+            bulk_in_endpoint,
+            &transfer,
+            &mut output_data[len..],
+            &mut scratch,
+            timeout,
+        )?;
+        output_data.truncate(len + bytes_read);
+
+        // a short transfer ends the message
+        if transfer.transfer_size < requested_size {
+            break;
+        }
+    }
 
     Ok(output_data)
 }
@@ -167,9 +608,7 @@ pub fn device_dependent_msg_out_header(
     header[2] = !btag;
 
     let transfer_size: [u8; 4] = transfer_size.to_le_bytes();
-    for n in 0..4 {
-        header[n + 4] = transfer_size[n];
-    }
+    header[4..8].copy_from_slice(&transfer_size);
 
     if end_of_message {
         header[8] = 0b0000_0001;
@@ -178,6 +617,16 @@ pub fn device_dependent_msg_out_header(
     Ok(header)
 }
 
+pub fn trigger_header(btag: u8) -> Result<[u8; 12]> {
+    let mut header: [u8; 12] = [0x00; 12];
+
+    header[0] = bulk_msg_id::TRIGGER;
+    header[1] = btag;
+    header[2] = !btag;
+
+    Ok(header)
+}
+
 pub fn request_device_dependent_msg_in_header(
     btag: u8,
     transfer_size: u32,
@@ -190,22 +639,17 @@ pub fn request_device_dependent_msg_in_header(
     header[2] = !header[1];
 
     let transfer_size: [u8; 4] = transfer_size.to_le_bytes();
-    for n in 0..4 {
-        header[n + 4] = transfer_size[n];
-    }
+    header[4..8].copy_from_slice(&transfer_size);
 
-    match term_char {
-        Some(tc) => {
-            header[8] = 0b0000_0010;
-            header[9] = tc;
-        }
-        None => {}
+    if let Some(tc) = term_char {
+        header[8] = 0b0000_0010;
+        header[9] = tc;
     }
 
     Ok(header)
 }
 
-pub fn _vendor_specific_out_header(btag: u8, transfer_size: u32) -> Result<[u8; 12]> {
+pub fn vendor_specific_out_header(btag: u8, transfer_size: u32) -> Result<[u8; 12]> {
     let mut header: [u8; 12] = [0x00; 12];
 
     header[0] = bulk_msg_id::VENDOR_SPECIFIC_MSG_OUT;
@@ -213,14 +657,12 @@ pub fn _vendor_specific_out_header(btag: u8, transfer_size: u32) -> Result<[u8;
     header[2] = !btag;
 
     let transfer_size: [u8; 4] = transfer_size.to_le_bytes();
-    for n in 0..4 {
-        header[n + 4] = transfer_size[n];
-    }
+    header[4..8].copy_from_slice(&transfer_size);
 
     Ok(header)
 }
 
-pub fn _request_vendor_specific_in_header(btag: u8, transfer_size: u32) -> Result<[u8; 12]> {
+pub fn request_vendor_specific_in_header(btag: u8, transfer_size: u32) -> Result<[u8; 12]> {
     let mut header: [u8; 12] = [0x00; 12];
 
     header[0] = bulk_msg_id::REQUEST_VENDOR_SPECIFIC_MSG_IN;
@@ -228,9 +670,7 @@ pub fn _request_vendor_specific_in_header(btag: u8, transfer_size: u32) -> Resul
     header[2] = !btag;
 
     let transfer_size: [u8; 4] = transfer_size.to_le_bytes();
-    for n in 0..4 {
-        header[n + 4] = transfer_size[n];
-    }
+    header[4..8].copy_from_slice(&transfer_size);
 
     Ok(header)
 }
@@ -3,14 +3,17 @@
 //! Set of control requests to send to the device.
 //!
 
+use crate::communication::interrupt::{Listener, SRQ_NOTIFICATION};
 use crate::constants::control_requests::READ_STATUS_BYTE;
-use crate::constants::{control_requests, usbtmc_status};
-use crate::error::Error;
-use crate::types::{Capabilities, CtlBTag, Endpoint, Handle, Timeout};
+use crate::constants::{control_requests, misc, usbtmc_status};
+use crate::error::{Error, Result};
+use crate::status::StatusByte;
+use crate::types::{Capabilities, CtlBTag, Endpoint, Handle, Timeout, Usb488Capabilities};
 
-use anyhow::Result;
 use rusb::{Direction, TransferType};
 
+use std::time::{Duration, Instant};
+
 pub fn get_capabilities(
     handle: &Handle,
     interface_number: u8,
@@ -34,14 +37,14 @@ pub fn get_capabilities(
         w_value,
         w_index,
         &mut buffer,
-        timeout.borrow().clone(),
+        *timeout.borrow(),
     )?;
 
     // verify the status
     let status = buffer[0];
     match status {
         usbtmc_status::STATUS_SUCCESS => {}
-        _ => return Err(Error::StatusUnexpectedFailure.into()),
+        status => return Err(Error::StatusUnexpectedFailure(status)),
     };
 
     // get the bcd_version
@@ -56,18 +59,35 @@ pub fn get_capabilities(
     let is_listen_only: bool = interface_capabilities & 0b0000_0001 != 0;
     let supports_bulk_in_term_char: bool = device_capabilities & 0b0000_0001 != 0;
 
+    // get the USB488 subclass capabilities
+    let usb488_bcd_version: u16 = u16::from_le_bytes([buffer[12], buffer[13]]);
+    let usb488_interface_capabilities = buffer[14];
+    let usb488_device_capabilities = buffer[15];
+
+    let usb488 = Usb488Capabilities {
+        bcd_version: usb488_bcd_version,
+        is_488_2: usb488_interface_capabilities & 0b0000_0100 != 0,
+        accepts_remote_local_requests: usb488_interface_capabilities & 0b0000_0010 != 0,
+        accepts_trigger: usb488_interface_capabilities & 0b0000_0001 != 0,
+        is_scpi_compliant: usb488_device_capabilities & 0b0000_1000 != 0,
+        is_sr1_capable: usb488_device_capabilities & 0b0000_0100 != 0,
+        is_rl1_capable: usb488_device_capabilities & 0b0000_0010 != 0,
+        is_dt1_capable: usb488_device_capabilities & 0b0000_0001 != 0,
+    };
+
     Ok(Capabilities {
         bcd_version,
         accepts_indicator_pulse_request,
         is_talk_only,
         is_listen_only,
         supports_bulk_in_term_char,
+        usb488,
     })
 }
 
 /// ### Abort Bulk Out Transfer
 ///
-/// Abort a transfer on the bulk out endpoint.
+/// Abort a transfer on the bulk out endpoint, then clear the halt of the endpoint.
 ///
 /// #### Arguments
 /// - `handle` -> the device handle to the USB device
@@ -78,7 +98,7 @@ pub fn get_capabilities(
 /// #### Returns
 /// Returns the number of bytes the device read before aborting the transfer
 ///
-pub fn _abort_bulk_out_transfer(
+pub fn abort_bulk_out_transfer(
     handle: &Handle,
     bulk_out_endpoint: &Endpoint,
     transfer_btag: u8,
@@ -91,7 +111,7 @@ pub fn _abort_bulk_out_transfer(
     if bulk_out_endpoint.direction != Direction::Out
         || bulk_out_endpoint.transfer_type != TransferType::Bulk
     {
-        return Err(Error::IncorrectEndpoint.into());
+        return Err(Error::IncorrectEndpoint);
     }
 
     // setup the request
@@ -101,8 +121,8 @@ pub fn _abort_bulk_out_transfer(
         rusb::Recipient::Endpoint,
     );
     let b_request = control_requests::INITIATE_ABORT_BULK_OUT;
-    let w_value = u16::from_le_bytes([0x00, transfer_btag]);
-    let w_index = u16::from_le_bytes([0x00, 0b0000_0000 | bulk_out_endpoint.address]);
+    let w_value = transfer_btag as u16;
+    let w_index = bulk_out_endpoint.address as u16;
     let mut buffer: [u8; 0x0002] = [0x00; 0x0002];
 
     // execute the command
@@ -112,18 +132,18 @@ pub fn _abort_bulk_out_transfer(
         w_value,
         w_index,
         &mut buffer,
-        timeout.borrow().clone(),
+        *timeout.borrow(),
     )?;
 
     // check the status
     let status = buffer[0];
     match status {
         usbtmc_status::STATUS_SUCCESS => {}
-        usbtmc_status::STATUS_FAILED => return Err(Error::StatusFailure.into()),
+        usbtmc_status::STATUS_FAILED => return Err(Error::StatusFailure),
         usbtmc_status::STATUS_TRANSFER_NOT_IN_PROGRESS => {
-            return Err(Error::StatusNoTransferInProgress.into())
+            return Err(Error::StatusNoTransferInProgress)
         }
-        _ => return Err(Error::StatusUnexpectedFailure.into()),
+        status => return Err(Error::StatusUnexpectedFailure(status)),
     };
 
     // CHECK STATUS
@@ -142,25 +162,30 @@ pub fn _abort_bulk_out_transfer(
             w_value,
             w_index,
             &mut buffer,
-            timeout.borrow().clone(),
+            *timeout.borrow(),
         )?;
         let status = buffer[0];
         match status {
             usbtmc_status::STATUS_PENDING => continue,
             usbtmc_status::STATUS_SUCCESS => break,
-            _ => return Err(Error::StatusUnexpectedFailure.into()),
+            status => return Err(Error::StatusUnexpectedFailure(status)),
         }
     }
 
     // get the bytes that the device received and did NOT discard
     let bytes_read = u32::from_le_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]) as usize;
 
+    // CLEAR HALT
+    // ==========
+    clear_feature(handle, bulk_out_endpoint)?;
+
     Ok(bytes_read)
 }
 
 /// ### Abort Bulk In Transfer
 ///
-/// Abort a transfer on the bulk in endpoint.
+/// Abort a transfer on the bulk in endpoint, reading and discarding what is left in the
+/// Bulk-IN FIFO of the device, then clear the halt of the endpoint.
 ///
 /// #### Arguments
 /// - `handle` -> the device handle to the USB device
@@ -171,7 +196,7 @@ pub fn _abort_bulk_out_transfer(
 /// #### Returns
 /// Returns the number of bytes the device transfered to the host
 ///
-pub fn _abort_bulk_in_transfer(
+pub fn abort_bulk_in_transfer(
     handle: &Handle,
     bulk_in_endpoint: &Endpoint,
     transfer_btag: u8,
@@ -184,7 +209,7 @@ pub fn _abort_bulk_in_transfer(
     if bulk_in_endpoint.direction != Direction::In
         || bulk_in_endpoint.transfer_type != TransferType::Bulk
     {
-        return Err(Error::IncorrectEndpoint.into());
+        return Err(Error::IncorrectEndpoint);
     }
 
     // setup the request
@@ -194,8 +219,8 @@ pub fn _abort_bulk_in_transfer(
         rusb::Recipient::Endpoint,
     );
     let b_request = control_requests::INITIATE_ABORT_BULK_IN;
-    let w_value = u16::from_le_bytes([0x00, transfer_btag]);
-    let w_index = u16::from_le_bytes([0x00, 0b1000_0000 | bulk_in_endpoint.address]);
+    let w_value = transfer_btag as u16;
+    let w_index = bulk_in_endpoint.address as u16;
     let mut buffer: [u8; 0x0002] = [0x00; 0x0002];
 
     // execute the command
@@ -205,20 +230,23 @@ pub fn _abort_bulk_in_transfer(
         w_value,
         w_index,
         &mut buffer,
-        timeout.borrow().clone(),
+        *timeout.borrow(),
     )?;
 
     // check the status
     let status = buffer[0];
     match status {
         usbtmc_status::STATUS_SUCCESS => {}
-        usbtmc_status::STATUS_FAILED => return Err(Error::StatusFailure.into()),
+        usbtmc_status::STATUS_FAILED => return Err(Error::StatusFailure),
         usbtmc_status::STATUS_TRANSFER_NOT_IN_PROGRESS => {
-            return Err(Error::StatusNoTransferInProgress.into())
+            return Err(Error::StatusNoTransferInProgress)
         }
-        _ => return Err(Error::StatusUnexpectedFailure.into()),
+        status => return Err(Error::StatusUnexpectedFailure(status)),
     };
 
+    // empty the FIFO of the device
+    drain_bulk_in(handle, bulk_in_endpoint, timeout)?;
+
     // CHECK STATUS
     // ==========
 
@@ -235,20 +263,20 @@ pub fn _abort_bulk_in_transfer(
             w_value,
             w_index,
             &mut buffer,
-            timeout.borrow().clone(),
+            *timeout.borrow(),
         )?;
         let status = buffer[0];
         match status {
             usbtmc_status::STATUS_PENDING => {
-                // check if the Bulk IN FIFO is filled or not
-                let fifo_is_empty: bool = buffer[1] ^ 0b0000_0001 == 0;
-                if !fifo_is_empty {
-                    return Err(Error::BulkInFIFONotEmpty.into());
+                // empty the Bulk IN FIFO if it still holds data
+                let fifo_has_data: bool = buffer[1] & 0b0000_0001 != 0;
+                if fifo_has_data {
+                    drain_bulk_in(handle, bulk_in_endpoint, timeout)?;
                 }
                 continue;
             }
             usbtmc_status::STATUS_SUCCESS => break,
-            _ => return Err(Error::StatusUnexpectedFailure.into()),
+            status => return Err(Error::StatusUnexpectedFailure(status)),
         }
     }
 
@@ -256,21 +284,55 @@ pub fn _abort_bulk_in_transfer(
     let bytes_transfered =
         u32::from_le_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]) as usize;
 
+    // CLEAR HALT
+    // ==========
+    clear_feature(handle, bulk_in_endpoint)?;
+
     Ok(bytes_transfered)
 }
 
+/// ### Drain Bulk In
+///
+/// Read and discard data from the BULK IN endpoint until a short packet is received, or
+/// there is nothing left to read.
+///
+fn drain_bulk_in(handle: &Handle, bulk_in_endpoint: &Endpoint, timeout: &Timeout) -> Result<()> {
+    let max_packet_size = bulk_in_endpoint.max_packet_size as usize;
+    let mut buffer: Vec<u8> = vec![0x00; max_packet_size];
+
+    loop {
+        match handle
+            .borrow()
+            .read_bulk(bulk_in_endpoint.address, &mut buffer, *timeout.borrow())
+        {
+            Ok(n) if n < max_packet_size => return Ok(()),
+            Ok(_) => continue,
+            Err(rusb::Error::Timeout) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
 /// ### Clear Buffers
 ///
 /// Clear all input and output buffers associated to the device.
 ///
+/// While the device clears its buffers, the data left in its Bulk-IN FIFO is read and discarded.
+///
 /// **WARNING: must abort all BULK transfers and prevent new ones before using this command.**
 ///
 /// #### Arguments
 /// - `handle` -> the device handle to the USB device
 /// - `interface_number` - the number of the interface to clear
+/// - `bulk_in_endpoint` - the endpoint for the BULK IN endpoint
 /// - `timeout` -> the timeout to use for requests
 ///
-pub fn clear_buffers(handle: &Handle, interface_number: u8, timeout: &Timeout) -> Result<()> {
+pub fn clear_buffers(
+    handle: &Handle,
+    interface_number: u8,
+    bulk_in_endpoint: &Endpoint,
+    timeout: &Timeout,
+) -> Result<()> {
     // INTIATE CLEAR
     // ==========
 
@@ -292,13 +354,13 @@ pub fn clear_buffers(handle: &Handle, interface_number: u8, timeout: &Timeout) -
         w_value,
         w_index,
         &mut buffer,
-        timeout.borrow().clone(),
+        *timeout.borrow(),
     )?;
 
     let status = buffer[0];
     match status {
         usbtmc_status::STATUS_SUCCESS => {}
-        _ => return Err(Error::StatusUnexpectedFailure.into()),
+        status => return Err(Error::StatusUnexpectedFailure(status)),
     };
 
     // CHECK CLEAR
@@ -316,27 +378,67 @@ pub fn clear_buffers(handle: &Handle, interface_number: u8, timeout: &Timeout) -
             w_value,
             w_index,
             &mut buffer,
-            timeout.borrow().clone(),
+            *timeout.borrow(),
         )?;
 
         let status = buffer[0];
         match status {
             usbtmc_status::STATUS_PENDING => {
-                // check if the Bulk IN FIFO is filled or not
-                let fifo_is_empty: bool = buffer[1] ^ 0b0000_0001 == 0;
-                if !fifo_is_empty {
-                    return Err(Error::BulkInFIFONotEmpty.into());
+                // empty the Bulk IN FIFO if it still holds data
+                let fifo_has_data: bool = buffer[1] & 0b0000_0001 != 0;
+                if fifo_has_data {
+                    drain_bulk_in(handle, bulk_in_endpoint, timeout)?;
                 }
                 continue;
             }
             usbtmc_status::STATUS_SUCCESS => break,
-            _ => return Err(Error::StatusUnexpectedFailure.into()),
+            status => return Err(Error::StatusUnexpectedFailure(status)),
         }
     }
 
     Ok(())
 }
 
+/// ### Indicator Pulse
+///
+/// Ask the device to turn on an activity indicator for a human detectable length of time,
+/// to identify it.
+///
+/// #### Arguments
+/// - `handle` -> the device handle to the USB device
+/// - `interface_number` - the number of the interface
+/// - `timeout` -> the timeout to use for requests
+///
+pub fn indicator_pulse(handle: &Handle, interface_number: u8, timeout: &Timeout) -> Result<()> {
+    // setup the request
+    let bm_request_type: u8 = rusb::request_type(
+        Direction::In,
+        rusb::RequestType::Class,
+        rusb::Recipient::Interface,
+    );
+    let b_request: u8 = control_requests::INDICATOR_PULSE;
+    let w_value: u16 = 0x0000;
+    let w_index: u16 = u16::from_le_bytes([interface_number, 0x00]);
+    let mut buffer: [u8; 0x0001] = [0x00; 0x0001];
+
+    // execute the request
+    handle.borrow().read_control(
+        bm_request_type,
+        b_request,
+        w_value,
+        w_index,
+        &mut buffer,
+        *timeout.borrow(),
+    )?;
+
+    // check that it is successful
+    match buffer[0] {
+        usbtmc_status::STATUS_SUCCESS => Ok(()),
+        usbtmc_status::STATUS_FAILED => Err(Error::StatusFailure),
+        status => Err(Error::StatusUnexpectedFailure(status)),
+    }
+}
+
 /// ### Clear Feature
 ///
 /// Clear any halt on the specified endpoint.
@@ -354,18 +456,36 @@ pub fn clear_feature(handle: &Handle, endpoint: &Endpoint) -> Result<()> {
 ///
 /// Read the status byte through the control endpoint.
 ///
+/// When the device has an INTERRUPT IN endpoint, the status byte is sent there. If a listener
+/// is reading the endpoint, the status byte is taken from it.
+///
+/// If the device can't queue the notification because its INTERRUPT IN FIFO is full, the
+/// FIFO is drained and the request is retried.
+///
 /// #### Arguments
 /// - `handle` -> the device handle to the USB device
+/// - `interface_number` - the number of the USB488 interface
+/// - `ctl_btag` -> the bTag generator for the request
+/// - `interrupt_endpoint` -> the INTERRUPT IN endpoint, if any
+/// - `listener` -> the listener of the INTERRUPT IN endpoint, if running
+/// - `timeout` -> the timeout to use for requests
 ///
 pub fn read_status_byte(
     handle: &Handle,
     interface_number: u8,
     ctl_btag: &CtlBTag,
     interrupt_endpoint: &Option<Endpoint>,
+    listener: Option<&Listener>,
     timeout: &Timeout,
-) -> Result<u8> {
-    // setup our bTag
-    let btag = ctl_btag.get();
+) -> Result<StatusByte> {
+    let timeout: Duration = *timeout.borrow();
+
+    // verify the endpoint is correct
+    if let Some(ep) = interrupt_endpoint {
+        if ep.direction != Direction::In || ep.transfer_type != TransferType::Interrupt {
+            return Err(Error::IncorrectEndpoint);
+        }
+    }
 
     // setup the request
     let bm_request_type = rusb::request_type(
@@ -374,10 +494,137 @@ pub fn read_status_byte(
         rusb::Recipient::Interface,
     );
     let b_request: u8 = READ_STATUS_BYTE;
-    let w_value: u16 = btag as u16;
     let w_index: u16 = u16::from_le_bytes([interface_number, 0x00]);
     let mut buffer: [u8; 0x0003] = [0x00; 0x0003];
 
+    let mut attempts = 0;
+    let btag = loop {
+        // setup our bTag
+        let btag = ctl_btag.get();
+        let w_value: u16 = btag as u16;
+
+        // send/read the request
+        handle.borrow().read_control(
+            bm_request_type,
+            b_request,
+            w_value,
+            w_index,
+            &mut buffer,
+            timeout,
+        )?;
+
+        // check that it is successful
+        match buffer[0] {
+            usbtmc_status::STATUS_SUCCESS => {}
+            usbtmc_status::STATUS_INTERRUPT_IN_BUSY => {
+                attempts += 1;
+                if attempts > misc::STATUS_BYTE_RETRIES {
+                    return Err(Error::StatusInterruptInBusy);
+                }
+                // make room in the FIFO of the device
+                match (interrupt_endpoint, listener) {
+                    (Some(_), Some(_)) => std::thread::sleep(misc::INTERRUPT_BUSY_DELAY),
+                    (Some(ep), None) => {
+                        let mut packet: Vec<u8> = notification_buffer(ep);
+                        match handle
+                            .borrow()
+                            .read_interrupt(ep.address, &mut packet, timeout)
+                        {
+                            Ok(_) | Err(rusb::Error::Timeout) => {}
+                            Err(e) => return Err(e.into()),
+                        }
+                    }
+                    (None, _) => {
+                        return Err(Error::StatusUnexpectedFailure(
+                            usbtmc_status::STATUS_INTERRUPT_IN_BUSY,
+                        ))
+                    }
+                }
+                continue;
+            }
+            usbtmc_status::STATUS_FAILED => return Err(Error::StatusFailure),
+            status => return Err(Error::StatusUnexpectedFailure(status)),
+        };
+
+        // check that btags match
+        if btag != buffer[1] {
+            return Err(Error::StatusMismatchedBTag);
+        }
+
+        break btag;
+    };
+
+    // check whether the device uses an interrupt endpoint or not
+    let ep = match (interrupt_endpoint, listener) {
+        // If the device doesn't use an interrupt endpoint, the status byte is in the response
+        (None, _) => return Ok(StatusByte::from(buffer[2])),
+        // If the endpoint is being listened to, the listener gets the status byte
+        (Some(_), Some(listener)) => return listener.wait_for_status_byte(btag, timeout),
+        (Some(ep), None) => ep,
+    };
+
+    // Otherwise, the status byte is read from the interrupt endpoint
+    let deadline = Instant::now() + timeout;
+    let mut packet: Vec<u8> = notification_buffer(ep);
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::Timeout);
+        }
+        let bytes_read = handle
+            .borrow()
+            .read_interrupt(ep.address, &mut packet, remaining)?;
+
+        // bNotify1 bit 7 is set for USB488 notifications, skip the vendor specific ones
+        if bytes_read == 0 || packet[0] & 0b1000_0000 == 0 {
+            continue;
+        }
+        if bytes_read != 2 {
+            return Err(Error::InvalidNotification);
+        }
+        // skip the service requests posted in the meantime, and the late answers to
+        // previous requests which timed out
+        if packet[0] == SRQ_NOTIFICATION || packet[0] & 0b0111_1111 != btag {
+            continue;
+        }
+
+        return Ok(StatusByte::from(packet[1]));
+    }
+}
+
+/// Buffer holding a whole packet of the INTERRUPT IN endpoint, as vendor specific
+/// notifications may be longer than the USB488 ones
+fn notification_buffer(interrupt_endpoint: &Endpoint) -> Vec<u8> {
+    vec![0x00; (interrupt_endpoint.max_packet_size as usize).max(2)]
+}
+
+/// ### USB488 Remote Local Request
+///
+/// Send one of the USB488 REN_CONTROL, GO_TO_LOCAL or LOCAL_LOCKOUT requests.
+///
+/// #### Arguments
+/// - `handle` -> the device handle to the USB device
+/// - `interface_number` - the number of the USB488 interface
+/// - `b_request` -> the request to send
+/// - `w_value` -> the value of the request
+/// - `timeout` -> the timeout to use for requests
+///
+fn remote_local_request(
+    handle: &Handle,
+    interface_number: u8,
+    b_request: u8,
+    w_value: u16,
+    timeout: &Timeout,
+) -> Result<()> {
+    // setup the request
+    let bm_request_type = rusb::request_type(
+        Direction::In,
+        rusb::RequestType::Class,
+        rusb::Recipient::Interface,
+    );
+    let w_index: u16 = u16::from_le_bytes([interface_number, 0x00]);
+    let mut buffer: [u8; 0x0001] = [0x00; 0x0001];
+
     // send/read the request
     handle.borrow().read_control(
         bm_request_type,
@@ -390,31 +637,71 @@ pub fn read_status_byte(
 
     // check that it is successful
     match buffer[0] {
-        usbtmc_status::STATUS_SUCCESS => {}
-        usbtmc_status::STATUS_FAILED => return Err(Error::StatusFailure.into()),
-        _ => return Err(Error::StatusUnexpectedFailure.into()),
-    };
-
-    // check that btags match
-    if btag != buffer[1] {
-        return Err(Error::StatusMismatchedBTag.into());
+        usbtmc_status::STATUS_SUCCESS => Ok(()),
+        usbtmc_status::STATUS_FAILED => Err(Error::StatusFailure),
+        status => Err(Error::StatusUnexpectedFailure(status)),
     }
+}
 
-    // check whether the device uses an interrupt endpoint or not
-    match interrupt_endpoint {
-        // If the device uses an interrupt endpoint, the status byte is read from there
-        Some(ep) => {
-            // get the data from the interrupt IN ep
-            let mut buf: Vec<u8> = Vec::new();
-            handle
-                .borrow()
-                .read_interrupt(ep.address, &mut buf, *timeout.borrow())?;
-            // check that the interrupt in ep responds with the correct btag
-            if btag != buf[0] {
-                return Err(Error::StatusMismatchedBTag.into());
-            }
-            Ok(buf[1])
-        }
-        None => Ok(buffer[2]),
-    }
+/// ### REN Control
+///
+/// Assert or deassert the Remote Enable (REN) line.
+///
+/// #### Arguments
+/// - `handle` -> the device handle to the USB device
+/// - `interface_number` - the number of the USB488 interface
+/// - `enable` -> whether to assert REN
+/// - `timeout` -> the timeout to use for requests
+///
+pub fn ren_control(
+    handle: &Handle,
+    interface_number: u8,
+    enable: bool,
+    timeout: &Timeout,
+) -> Result<()> {
+    remote_local_request(
+        handle,
+        interface_number,
+        control_requests::REN_CONTROL,
+        enable as u16,
+        timeout,
+    )
+}
+
+/// ### Go To Local
+///
+/// Return the device to local control (equivalent of the IEEE 488.1 GTL message).
+///
+/// #### Arguments
+/// - `handle` -> the device handle to the USB device
+/// - `interface_number` - the number of the USB488 interface
+/// - `timeout` -> the timeout to use for requests
+///
+pub fn go_to_local(handle: &Handle, interface_number: u8, timeout: &Timeout) -> Result<()> {
+    remote_local_request(
+        handle,
+        interface_number,
+        control_requests::GO_TO_LOCAL,
+        0x0000,
+        timeout,
+    )
+}
+
+/// ### Local Lockout
+///
+/// Disable the front panel local controls of the device (equivalent of the IEEE 488.1 LLO message).
+///
+/// #### Arguments
+/// - `handle` -> the device handle to the USB device
+/// - `interface_number` - the number of the USB488 interface
+/// - `timeout` -> the timeout to use for requests
+///
+pub fn local_lockout(handle: &Handle, interface_number: u8, timeout: &Timeout) -> Result<()> {
+    remote_local_request(
+        handle,
+        interface_number,
+        control_requests::LOCAL_LOCKOUT,
+        0x0000,
+        timeout,
+    )
 }
@@ -0,0 +1,249 @@
+//! Interrupt
+//!
+//! Background listener for the notifications posted on the INTERRUPT IN endpoint.
+//!
+//! USB488 devices use the endpoint for two kinds of notifications:
+//! - responses to READ_STATUS_BYTE requests, with bNotify1 set to `0x80 | bTag`
+//! - service requests (SRQ), with bNotify1 set to `0x81`
+//!
+//! In both cases, bNotify2 holds the status byte.
+//!
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::constants::misc;
+use crate::error::{Error, Result};
+use crate::status::StatusByte;
+use crate::types::{Endpoint, Handle};
+
+use rusb::{Direction, TransferType};
+
+/// bNotify1 value of a service request notification
+pub const SRQ_NOTIFICATION: u8 = 0x81;
+
+/// A function called on each service request
+type SrqCallback = Arc<dyn Fn(StatusByte) + Send + Sync>;
+
+#[derive(Default)]
+struct ListenerState {
+    /// Status bytes received in response to READ_STATUS_BYTE, by bTag
+    status_bytes: HashMap<u8, StatusByte>,
+    /// The status byte of the last service request not yet waited for
+    srq: Option<StatusByte>,
+    senders: Vec<mpsc::Sender<StatusByte>>,
+    callbacks: Vec<SrqCallback>,
+    /// The listener stopped on an error (such as the device being disconnected)
+    stopped: bool,
+}
+
+/// ### Listener
+///
+/// Thread reading the INTERRUPT IN endpoint, sorting the notifications into status byte
+/// responses and service requests.
+///
+/// The service request callbacks are called from a second thread, so that they can make
+/// requests to the device themselves.
+///
+/// The threads are stopped when the listener is dropped.
+///
+pub struct Listener {
+    shared: Arc<(Mutex<ListenerState>, Condvar)>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl std::fmt::Debug for Listener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Listener").finish_non_exhaustive()
+    }
+}
+
+impl Listener {
+    /// ### Start
+    ///
+    /// Start listening to the INTERRUPT IN endpoint.
+    ///
+    pub fn start(handle: &Handle, interrupt_endpoint: &Endpoint) -> Result<Listener> {
+        // verify the endpoint is correct
+        if interrupt_endpoint.direction != Direction::In
+            || interrupt_endpoint.transfer_type != TransferType::Interrupt
+        {
+            return Err(Error::IncorrectEndpoint);
+        }
+
+        let shared: Arc<(Mutex<ListenerState>, Condvar)> = Arc::default();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        // the dispatcher stops once the listening thread drops the sender
+        let (srq_sender, srq_receiver) = mpsc::channel();
+        {
+            let shared = shared.clone();
+            std::thread::spawn(move || dispatch(srq_receiver, shared));
+        }
+
+        let thread = {
+            let handle = handle.clone();
+            let endpoint = interrupt_endpoint.clone();
+            let shared = shared.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || listen(handle, endpoint, shared, stop, srq_sender))
+        };
+
+        Ok(Listener {
+            shared,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    fn state(&self) -> MutexGuard<'_, ListenerState> {
+        self.shared.0.lock().unwrap()
+    }
+
+    /// ### On SRQ
+    ///
+    /// Register a function called with the status byte of each service request.
+    ///
+    pub fn on_srq(&self, callback: impl Fn(StatusByte) + Send + Sync + 'static) {
+        self.state().callbacks.push(Arc::new(callback));
+    }
+
+    /// ### Subscribe
+    ///
+    /// Return a channel receiving the status byte of each service request.
+    ///
+    pub fn subscribe(&self) -> mpsc::Receiver<StatusByte> {
+        let (sender, receiver) = mpsc::channel();
+        self.state().senders.push(sender);
+        receiver
+    }
+
+    /// ### Wait For SRQ
+    ///
+    /// Wait for a service request and return its status byte.
+    ///
+    /// A service request received since the last wait is returned immediately.
+    /// Returns `None` if no service request was received before the timeout.
+    ///
+    pub fn wait_for_srq(&self, timeout: Duration) -> Result<Option<StatusByte>> {
+        let (mut state, _) = self
+            .shared
+            .1
+            .wait_timeout_while(self.state(), timeout, |state| {
+                state.srq.is_none() && !state.stopped
+            })
+            .unwrap();
+
+        match state.srq.take() {
+            Some(status_byte) => Ok(Some(status_byte)),
+            None if state.stopped => Err(Error::InterruptListenerStopped),
+            None => Ok(None),
+        }
+    }
+
+    /// ### Wait For Status Byte
+    ///
+    /// Wait for the response to the READ_STATUS_BYTE request identified by `btag`.
+    ///
+    pub fn wait_for_status_byte(&self, btag: u8, timeout: Duration) -> Result<StatusByte> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state();
+
+        loop {
+            if let Some(status_byte) = state.status_bytes.remove(&btag) {
+                return Ok(status_byte);
+            }
+            if state.stopped {
+                return Err(Error::InterruptListenerStopped);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Error::Timeout);
+            }
+            state = self.shared.1.wait_timeout(state, deadline - now).unwrap().0;
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Read the INTERRUPT IN endpoint until stopped
+fn listen(
+    handle: Handle,
+    interrupt_endpoint: Endpoint,
+    shared: Arc<(Mutex<ListenerState>, Condvar)>,
+    stop: Arc<AtomicBool>,
+    srq_sender: mpsc::Sender<StatusByte>,
+) {
+    let (lock, condvar) = &*shared;
+    // vendor specific notifications may fill a whole packet
+    let mut buffer: Vec<u8> = vec![0x00; (interrupt_endpoint.max_packet_size as usize).max(2)];
+
+    while !stop.load(Ordering::Relaxed) {
+        let bytes_read = match handle.borrow().read_interrupt(
+            interrupt_endpoint.address,
+            &mut buffer,
+            misc::INTERRUPT_POLL_DURATION,
+        ) {
+            Ok(n) => n,
+            Err(rusb::Error::Timeout) => continue,
+            // a packet longer than the endpoint announced isn't a USB488 notification
+            Err(rusb::Error::Overflow) => continue,
+            Err(_) => {
+                lock.lock().unwrap().stopped = true;
+                condvar.notify_all();
+                return;
+            }
+        };
+
+        // ignore packets which aren't USB488 notifications
+        if bytes_read != 2 || buffer[0] & 0b1000_0000 == 0 {
+            continue;
+        }
+        let status_byte = StatusByte::from(buffer[1]);
+
+        if buffer[0] != SRQ_NOTIFICATION {
+            // response to READ_STATUS_BYTE
+            let btag = buffer[0] & 0b0111_1111;
+            lock.lock().unwrap().status_bytes.insert(btag, status_byte);
+            condvar.notify_all();
+            continue;
+        }
+
+        // service request
+        {
+            let mut state = lock.lock().unwrap();
+            state.srq = Some(status_byte);
+            state
+                .senders
+                .retain(|sender| sender.send(status_byte).is_ok());
+        }
+        condvar.notify_all();
+        let _ = srq_sender.send(status_byte);
+    }
+}
+
+/// Call the service request callbacks until the listening thread stops
+fn dispatch(
+    srq_receiver: mpsc::Receiver<StatusByte>,
+    shared: Arc<(Mutex<ListenerState>, Condvar)>,
+) {
+    for status_byte in srq_receiver {
+        let callbacks = shared.0.lock().unwrap().callbacks.clone();
+        for callback in callbacks {
+            callback(status_byte);
+        }
+    }
+}
@@ -21,10 +21,20 @@ pub mod misc {
     pub const DEFAULT_TIMEOUT_DURATION: Duration = Duration::from_secs(2);
     /// The size in bytes of a USBTMC header in a bulk transfer
     pub const USBTMC_HEADER_SIZE: usize = 12;
-    /// Buffer size we define for the application
-    pub const APPLICATION_BUFFER_SIZE: u32 = 1024 * 8;
+    /// Default largest payload sent to the device in a single message
+    pub const DEFAULT_MESSAGE_SIZE: u32 = 1024 * 8;
+    /// Default largest TransferSize requested from the device in a single transfer
+    pub const DEFAULT_TRANSFER_SIZE: u32 = 1024 * 1024;
     /// Default termination character to use (using NI-VISA default '\n')
     pub const DEFAULT_TERM_CHAR: u8 = b'\n';
+    /// How long the interrupt listener waits for a notification before checking if it must stop
+    pub const INTERRUPT_POLL_DURATION: Duration = Duration::from_millis(100);
+    /// How many times READ_STATUS_BYTE is retried when the INTERRUPT IN FIFO of the device is full
+    pub const STATUS_BYTE_RETRIES: usize = 3;
+    /// How long to wait for the interrupt listener to drain the INTERRUPT IN FIFO of the device
+    pub const INTERRUPT_BUSY_DELAY: Duration = Duration::from_millis(10);
+    /// The most errors read with `SYST:ERR?` when draining the error queue of the device
+    pub const ERROR_QUEUE_MAX_READS: usize = 64;
 }
 
 #[allow(unused)]
@@ -56,6 +66,9 @@ pub mod control_requests {
     pub const GET_CAPABILITIES: u8 = 7;
     pub const INDICATOR_PULSE: u8 = 64;
     pub const READ_STATUS_BYTE: u8 = 128;
+    pub const REN_CONTROL: u8 = 160;
+    pub const GO_TO_LOCAL: u8 = 161;
+    pub const LOCAL_LOCKOUT: u8 = 162;
 }
 
 #[allow(unused)]
@@ -66,4 +79,5 @@ pub mod bulk_msg_id {
     pub const REQUEST_VENDOR_SPECIFIC_MSG_IN: u8 = 127;
     pub const DEVICE_DEPENDENT_MSG_IN: u8 = 2;
     pub const VENDOR_SPECIFIC_MSG_IN: u8 = 127;
+    pub const TRIGGER: u8 = 128;
 }
@@ -3,11 +3,29 @@
 //! The errors used throughout the crate.
 //!
 
-#[allow(unused)]
+use std::fmt;
+
+/// A result with the [`Error`] of the crate.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// ### Error
+///
+/// The errors returned by the client.
+///
+/// Errors returned by the client methods are wrapped in [`Error::Context`] to tell which
+/// operation failed. [`Error::without_context`] returns the underlying error to match on,
+/// and [`Error::is_timeout`] and [`Error::is_disconnected`] cover the most common checks.
+///
+/// Like the other errors wrapping an underlying error, the context doesn't display it: it is
+/// returned by [`std::error::Error::source`] for error reporters to walk the chain.
+///
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum Error {
     #[error("device not found")]
     DeviceNotFound,
+    #[error("invalid VISA resource string {0:?}")]
+    InvalidResource(String),
     #[error("device is not compatible with USBTMC")]
     DeviceIncompatible,
     #[error("specified configuration not found")]
@@ -20,16 +38,285 @@ pub enum Error {
     BulkOutEndpointNotFound,
     #[error("bulk in endpoint not found")]
     BulkInEndpointNotFound,
+    #[error("interrupt in endpoint not found")]
+    InterruptEndpointNotFound,
     #[error("used incorrect endpoint")]
     IncorrectEndpoint,
-    #[error("bulk in transfer cannot be aborted because FIFO is not empty")]
-    BulkInFIFONotEmpty,
+    #[error("operation timed out")]
+    Timeout,
+    #[error("device disconnected")]
+    Disconnected,
+    #[error("usb error")]
+    Usb(#[source] rusb::Error),
     #[error("no transfer in progress")]
     StatusNoTransferInProgress,
     #[error("control request failed")]
     StatusFailure,
-    #[error("control request unexpectedly failed")]
-    StatusUnexpectedFailure,
+    #[error("control request returned the unexpected status {0:#04x}")]
+    StatusUnexpectedFailure(u8),
     #[error("mismatched bTag")]
     StatusMismatchedBTag,
+    #[error("interrupt in FIFO of the device is full")]
+    StatusInterruptInBusy,
+    #[error("invalid notification on the interrupt in endpoint")]
+    InvalidNotification,
+    #[error("device does not accept the REN_CONTROL, GO_TO_LOCAL and LOCAL_LOCKOUT requests")]
+    RemoteLocalNotSupported,
+    #[error("device does not accept the INDICATOR_PULSE request")]
+    IndicatorPulseNotSupported,
+    #[error("interrupt in listener stopped")]
+    InterruptListenerStopped,
+    #[error("empty transfer ending a message")]
+    EmptyMessage,
+    #[error("bulk in transfer too short to hold a header")]
+    BulkInShortHeader,
+    #[error("unexpected MsgID {0} in bulk in header")]
+    BulkInUnexpectedMsgId(u8),
+    #[error("mismatched bTag in bulk in header (expected {expected}, received {received})")]
+    BulkInMismatchedBTag { expected: u8, received: u8 },
+    #[error(
+        "bulk in TransferSize of {transfer_size} bytes larger than the {requested} bytes requested"
+    )]
+    BulkInTransferSizeExceeded {
+        transfer_size: usize,
+        requested: usize,
+    },
+    #[error("bulk in transfer ended after {received} of {transfer_size} bytes")]
+    BulkInShortTransfer {
+        received: usize,
+        transfer_size: usize,
+    },
+    #[error("malformed IEEE 488.2 block header")]
+    InvalidBlockHeader,
+    #[error("block of {length} bytes is not a multiple of the {element_size} bytes values")]
+    InvalidBlockLength { length: usize, element_size: usize },
+    #[error("block ended after {received} of {expected} bytes")]
+    IncompleteBlock { received: usize, expected: usize },
+    #[error("unexpected response {0:?}")]
+    InvalidResponse(String),
+    #[error("instrument reported {}", join_errors(.0))]
+    Instrument(Vec<InstrumentError>),
+    #[error("response is not valid utf-8")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("i/o error")]
+    Io(#[from] std::io::Error),
+    #[error("bulk out transfer aborted after an error")]
+    BulkOutTransferAborted(#[source] Box<Error>),
+    #[error("bulk in transfer aborted after an error")]
+    BulkInTransferAborted(#[source] Box<Error>),
+    #[error("{context}")]
+    Context {
+        context: ErrorContext,
+        #[source]
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// ### Without Context
+    ///
+    /// Return the error without the context of the operation which failed.
+    ///
+    pub fn without_context(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.without_context(),
+            error => error,
+        }
+    }
+
+    /// ### Context
+    ///
+    /// Return the context of the operation which failed, if any.
+    ///
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Error::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// ### Is Timeout
+    ///
+    /// Whether the device did not answer in time, including when the transfer was aborted
+    /// after timing out.
+    ///
+    pub fn is_timeout(&self) -> bool {
+        match self.without_context() {
+            Error::Timeout => true,
+            Error::BulkOutTransferAborted(e) | Error::BulkInTransferAborted(e) => e.is_timeout(),
+            _ => false,
+        }
+    }
+
+    /// ### Instrument Errors
+    ///
+    /// Return the errors reported by the instrument in checked mode, if any.
+    ///
+    pub fn instrument_errors(&self) -> Option<&[InstrumentError]> {
+        match self.without_context() {
+            Error::Instrument(errors) => Some(errors),
+            _ => None,
+        }
+    }
+
+    /// ### Is Disconnected
+    ///
+    /// Whether the device is no longer connected.
+    ///
+    pub fn is_disconnected(&self) -> bool {
+        matches!(self.without_context(), Error::Disconnected)
+    }
+
+    /// Add the context of the failed operation, the innermost context is kept if there is one
+    pub(crate) fn with_context(self, context: ErrorContext) -> Error {
+        match self {
+            Error::Context {
+                context: mut inner,
+                source,
+            } => {
+                if inner.command.is_none() {
+                    inner.command = context.command;
+                }
+                Error::Context {
+                    context: inner,
+                    source,
+                }
+            }
+            error => Error::Context {
+                context,
+                source: Box::new(error),
+            },
+        }
+    }
+}
+
+impl From<rusb::Error> for Error {
+    fn from(error: rusb::Error) -> Error {
+        match error {
+            rusb::Error::Timeout => Error::Timeout,
+            rusb::Error::NoDevice => Error::Disconnected,
+            error => Error::Usb(error),
+        }
+    }
+}
+
+/// ### Instrument Error
+///
+/// An error read from the SCPI error queue of the instrument with `SYST:ERR?`, such as
+/// `-113,"Undefined header"`.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstrumentError {
+    /// The error code, negative for the errors defined by SCPI
+    pub code: i32,
+    /// The description of the error
+    pub message: String,
+    /// The command after which the error was read
+    pub command: Option<String>,
+}
+
+impl InstrumentError {
+    /// Parse a `SYST:ERR?` response, the message being optional
+    pub(crate) fn parse(response: &str) -> Option<InstrumentError> {
+        let (code, message) = match response.split_once(',') {
+            Some((code, message)) => (code, message.trim()),
+            None => (response, ""),
+        };
+        let code: i32 = code.trim().trim_start_matches('+').parse().ok()?;
+        let message = message
+            .strip_prefix('"')
+            .and_then(|m| m.strip_suffix('"'))
+            .unwrap_or(message);
+
+        Some(InstrumentError {
+            code,
+            message: message.to_string(),
+            command: None,
+        })
+    }
+}
+
+impl fmt::Display for InstrumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{:?}", self.code, self.message)?;
+        if let Some(command) = &self.command {
+            write!(f, " after {:?}", command)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Join the errors of the instrument in a single line
+fn join_errors(errors: &[InstrumentError]) -> String {
+    errors
+        .iter()
+        .map(InstrumentError::to_string)
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
+/// ### Error Context
+///
+/// The operation during which an error happened.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ErrorContext {
+    /// The operation which failed
+    pub operation: &'static str,
+    /// The bTag of the last bulk message of the operation
+    pub btag: Option<u8>,
+    /// The address of the endpoint used by the operation
+    pub endpoint: Option<u8>,
+    /// The command sent to the device
+    pub command: Option<String>,
+}
+
+impl ErrorContext {
+    pub(crate) fn new(operation: &'static str) -> ErrorContext {
+        ErrorContext {
+            operation,
+            btag: None,
+            endpoint: None,
+            command: None,
+        }
+    }
+
+    pub(crate) fn btag(mut self, btag: u8) -> ErrorContext {
+        self.btag = Some(btag);
+        self
+    }
+
+    pub(crate) fn endpoint(mut self, endpoint: u8) -> ErrorContext {
+        self.endpoint = Some(endpoint);
+        self
+    }
+
+    pub(crate) fn command(mut self, command: &str) -> ErrorContext {
+        self.command = Some(command.to_string());
+        self
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed", self.operation)?;
+
+        let mut details: Vec<String> = Vec::new();
+        if let Some(command) = &self.command {
+            details.push(format!("command {:?}", command));
+        }
+        if let Some(btag) = self.btag {
+            details.push(format!("bTag {}", btag));
+        }
+        if let Some(endpoint) = self.endpoint {
+            details.push(format!("endpoint {:#04x}", endpoint));
+        }
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+
+        Ok(())
+    }
 }
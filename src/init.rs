@@ -5,12 +5,14 @@
 
 use crate::{
     constants::usb::*,
-    error::Error,
-    types::{DeviceAddr, DeviceId, DeviceInfo, DeviceMode, Endpoint, UsbtmcEndpoints},
+    error::{Error, Result},
+    resource::VisaResource,
+    types::{
+        DeviceAddr, DeviceId, DeviceInfo, DeviceMode, Endpoint, SerialNumber, UsbtmcEndpoints,
+    },
     DeviceFilter,
 };
 
-use anyhow::Result;
 use rusb::{Context, Device, DeviceDescriptor, DeviceHandle, Direction, TransferType, UsbContext};
 
 /// Get first found TMC device
@@ -75,7 +77,7 @@ impl DeviceFilter for (u16, u16) {
         _device: &Device<T>,
         device_desc: &DeviceDescriptor,
     ) -> bool {
-        self.0 == device_desc.vendor_id() && self.0 == device_desc.product_id()
+        self.0 == device_desc.vendor_id() && self.1 == device_desc.product_id()
     }
 }
 
@@ -101,6 +103,42 @@ impl DeviceFilter for DeviceInfo {
     }
 }
 
+/// Get TMC device by serial number
+impl DeviceFilter for SerialNumber {
+    fn apply_filter<T: UsbContext>(
+        &self,
+        device: &Device<T>,
+        device_desc: &DeviceDescriptor,
+    ) -> bool {
+        read_serial_number(device, device_desc).as_ref() == Some(&self.0)
+    }
+}
+
+/// Get TMC device by VISA resource string (identifiers, serial number and interface)
+impl DeviceFilter for VisaResource {
+    fn apply_filter<T: UsbContext>(
+        &self,
+        device: &Device<T>,
+        device_desc: &DeviceDescriptor,
+    ) -> bool {
+        if self.vendor_id != device_desc.vendor_id() || self.product_id != device_desc.product_id()
+        {
+            return false;
+        }
+
+        match &self.serial_number {
+            Some(serial_number) => {
+                read_serial_number(device, device_desc).as_ref() == Some(serial_number)
+            }
+            None => true,
+        }
+    }
+
+    fn interface_number(&self) -> Option<u8> {
+        self.interface_number
+    }
+}
+
 /// Allow apply filter by reference
 impl<T: DeviceFilter> DeviceFilter for &T {
     fn apply_filter<X: UsbContext>(
@@ -110,6 +148,10 @@ impl<T: DeviceFilter> DeviceFilter for &T {
     ) -> bool {
         (**self).apply_filter(device, device_desc)
     }
+
+    fn interface_number(&self) -> Option<u8> {
+        (**self).interface_number()
+    }
 }
 
 /// Allow apply filter by Rc
@@ -121,6 +163,10 @@ impl<T: DeviceFilter> DeviceFilter for std::rc::Rc<T> {
     ) -> bool {
         (**self).apply_filter(device, device_desc)
     }
+
+    fn interface_number(&self) -> Option<u8> {
+        (**self).interface_number()
+    }
 }
 
 /// Allow apply filter by Arc
@@ -132,6 +178,24 @@ impl<T: DeviceFilter> DeviceFilter for std::sync::Arc<T> {
     ) -> bool {
         (**self).apply_filter(device, device_desc)
     }
+
+    fn interface_number(&self) -> Option<u8> {
+        (**self).interface_number()
+    }
+}
+
+/// Read the serial number string descriptor, opening the device if needed
+fn read_serial_number<T: UsbContext>(
+    device: &Device<T>,
+    device_desc: &DeviceDescriptor,
+) -> Option<String> {
+    device_desc.serial_number_string_index()?;
+
+    device
+        .open()
+        .ok()?
+        .read_serial_number_string_ascii(device_desc)
+        .ok()
 }
 
 fn is_tmc_device<T: UsbContext>(device: &Device<T>, device_desc: &DeviceDescriptor) -> bool {
@@ -161,6 +225,11 @@ pub fn list_devices<T: UsbContext>(context: &mut T) -> Result<Vec<DeviceInfo>> {
         .filter_map(|device| {
             let device_desc = device.device_descriptor().ok()?;
             if is_tmc_device(&device, &device_desc) {
+                // the strings can only be read from an opened device
+                let handle = device.open().ok();
+                let read_string =
+                    |index: Option<u8>| handle.as_ref()?.read_string_descriptor_ascii(index?).ok();
+
                 Some(DeviceInfo {
                     id: DeviceId {
                         vendor_id: device_desc.vendor_id(),
@@ -170,6 +239,9 @@ pub fn list_devices<T: UsbContext>(context: &mut T) -> Result<Vec<DeviceInfo>> {
                         bus: device.bus_number(),
                         device: device.address(),
                     },
+                    manufacturer: read_string(device_desc.manufacturer_string_index()),
+                    product: read_string(device_desc.product_string_index()),
+                    serial_number: read_string(device_desc.serial_number_string_index()),
                 })
             } else {
                 None
@@ -203,14 +275,18 @@ pub fn open_device<T: UsbContext>(
         }
     }
 
-    Err(Error::DeviceNotFound.into())
+    Err(Error::DeviceNotFound)
 }
 
 /// ### Get USBTMC Mode
 ///
 /// Get the device mode (configuration, interface and interface setting) that is compatible with USBTMC.
+/// If `interface_number` is given, only that interface is used.
 ///
-pub fn get_usbtmc_mode(device: &Device<Context>) -> Result<DeviceMode> {
+pub fn get_usbtmc_mode(
+    device: &Device<Context>,
+    interface_number: Option<u8>,
+) -> Result<DeviceMode> {
     // setup the output
     let mut modes: Vec<DeviceMode> = Vec::new();
 
@@ -242,10 +318,16 @@ pub fn get_usbtmc_mode(device: &Device<Context>) -> Result<DeviceMode> {
         }
     }
 
-    // Get the first mode
-    let mode = match modes.first() {
-        Some(m) => m,
-        None => return Err(Error::DeviceIncompatible.into()),
+    // Get the first mode, on the requested interface if any
+    let mode = match interface_number {
+        Some(number) => match modes.iter().find(|m| m.interface_number == number) {
+            Some(m) => m,
+            None => return Err(Error::InterfaceNotFound),
+        },
+        None => match modes.first() {
+            Some(m) => m,
+            None => return Err(Error::DeviceIncompatible),
+        },
     };
 
     Ok(mode.clone())
@@ -286,7 +368,7 @@ pub fn get_endpoints(mode: &DeviceMode, device: &Device<Context>) -> Result<Usbt
         .find(|inter| inter.number() == mode.interface_number)
     {
         Some(i) => i,
-        None => return Err(Error::InterfaceNotFound.into()),
+        None => return Err(Error::InterfaceNotFound),
     };
     // get the interface descriptor (setting)
     let interface_desc = match interface
@@ -294,7 +376,7 @@ pub fn get_endpoints(mode: &DeviceMode, device: &Device<Context>) -> Result<Usbt
         .find(|d| d.setting_number() == mode.setting_number)
     {
         Some(desc) => desc,
-        None => return Err(Error::InterfaceSettingNotFound.into()),
+        None => return Err(Error::InterfaceSettingNotFound),
     };
 
     // With the descriptor, we can now iterate through the endpoints
@@ -313,22 +395,19 @@ pub fn get_endpoints(mode: &DeviceMode, device: &Device<Context>) -> Result<Usbt
         .find(|ep| ep.transfer_type == TransferType::Bulk && ep.direction == Direction::Out)
     {
         Some(ep) => ep.clone(),
-        None => return Err(Error::BulkOutEndpointNotFound.into()),
+        None => return Err(Error::BulkOutEndpointNotFound),
     };
     let bulk_in_ep = match endpoints_list
         .iter()
         .find(|ep| ep.transfer_type == TransferType::Bulk && ep.direction == Direction::In)
     {
         Some(ep) => ep.clone(),
-        None => return Err(Error::BulkInEndpointNotFound.into()),
+        None => return Err(Error::BulkInEndpointNotFound),
     };
-    let interrupt_ep = match endpoints_list
+    let interrupt_ep = endpoints_list
         .iter()
         .find(|ep| ep.transfer_type == TransferType::Interrupt && ep.direction == Direction::In)
-    {
-        Some(ep) => Some(ep.clone()),
-        None => None,
-    };
+        .cloned();
 
     Ok(UsbtmcEndpoints {
         bulk_out_ep,
@@ -2,9 +2,11 @@
 //!
 //! Pure Rust implementation of the USBTMC protocol to connect to instruments.
 //!
-//! Thus far, this library implements the basic USBTMC control endpoint commands,
-//! writing DEVICE_DEPENDENT messages to the BULK OUT endpoint and reading DEVICE_DEPENDENT
-//! messages to the BULK IN endpoint.
+//! This library implements the USBTMC protocol and its USB488 subclass:
+//! - the USBTMC control requests (capabilities, clear, abort and indicator pulse)
+//! - DEVICE_DEPENDENT and VENDOR_SPECIFIC messages on the BULK OUT and BULK IN endpoints
+//! - the USB488 requests (READ_STATUS_BYTE, REN_CONTROL, GO_TO_LOCAL, LOCAL_LOCKOUT and TRIGGER)
+//! - the service requests posted on the INTERRUPT IN endpoint
 //!
 //! ## Usage
 //!
@@ -17,7 +19,7 @@
 //!
 //! The example below demonstrates how to connect to, send commands to and query the device.
 //!
-//! ```rust
+//! ```no_run
 //! use rs_usbtmc::UsbtmcClient;
 //!
 //! const DEVICE_VID: u16 = 0x0000;
@@ -25,7 +27,7 @@
 //!
 //! fn main() {
 //!     // connect to the device
-//!     let device = UsbtmcClient::connect(DEVICE_VID, DEVICE_PID).expect("failed to connect");
+//!     let device = UsbtmcClient::connect((DEVICE_VID, DEVICE_PID)).expect("failed to connect");
 //!
 //!     // send a command to the device
 //!     device.command("*IDN?").expect("failed to send command");
@@ -38,35 +40,77 @@
 //! }
 //! ```
 //!
+//! ## Errors
+//!
+//! Every fallible method returns an [`Error`]. Errors of the client methods are wrapped with
+//! an [`ErrorContext`] telling which operation failed, with the command, bTag and endpoint
+//! involved. Use [`Error::without_context`] to match on the underlying error, or
+//! [`Error::is_timeout`] and [`Error::is_disconnected`] to decide whether to retry.
+//!
+//! Errors of the instrument itself, such as an unknown command, are only reported in checked
+//! mode, enabled with [`UsbtmcClient::set_checked`].
+//!
+//! ## Testing Without Hardware
+//!
+//! The client talks to the device through a [`Transport`]. A [`MockTransport`] can be
+//! used in place of a real USB device to script the device responses, and the client is
+//! then created with [`UsbtmcClient::from_transport`].
+//!
+//! For higher level tests, the [`SimulatedDevice`] speaks the USBTMC protocol and answers
+//! registered queries.
+//!
 //! ## Project Plans
 //!
 //! I created this driver as part of a project to control an oscilloscope during a summer
-//! research position. Alone, I do not have access to //! an oscilloscope.
+//! research position. Alone, I do not have access to an oscilloscope.
+//! The USBTMC and USB488 requests are implemented and tested against a simulated device.
 //! If I do obtain one, the plan is to:
 //!
-//! - Fully implement all possible requests
-//! - Implement the usb488 subclass requests
+//! - Validate the driver against real instruments
 //!
 //! I'll reach out to my university for access to an instrument to complete this project, but I'm open to collaborating.
 //!
 
+mod block;
 mod constants;
 mod error;
 mod init;
+mod resource;
+mod simulator;
+mod status;
+mod stream;
+mod transport;
 mod types;
 mod communication {
     pub mod bulk;
     pub mod control;
+    pub mod interrupt;
 }
 
+pub use block::{BinaryValue, ByteOrder};
+pub use error::{Error, ErrorContext, InstrumentError, Result};
+pub use resource::VisaResource;
 use rusb::DeviceDescriptor;
-pub use types::{DeviceAddr, DeviceId, DeviceInfo};
+pub use simulator::SimulatedDevice;
+pub use status::{EventStatus, OperationStatus, QuestionableStatus, StatusByte};
+pub use stream::UsbtmcStream;
+pub use transport::{ControlRequest, MockTransport, Transport};
+pub use types::{
+    Capabilities, DeviceAddr, DeviceId, DeviceInfo, Endpoint, ReadEnd, Response, SerialNumber,
+    Usb488Capabilities, UsbtmcEndpoints,
+};
 
 use communication::control;
-use constants::misc::DEFAULT_TIMEOUT_DURATION;
-use types::{BTag, Capabilities, CtlBTag, DeviceMode, Handle, Timeout, UsbtmcEndpoints};
+use communication::interrupt::Listener;
+use constants::misc::{
+    DEFAULT_MESSAGE_SIZE, DEFAULT_TERM_CHAR, DEFAULT_TIMEOUT_DURATION, DEFAULT_TRANSFER_SIZE,
+    ERROR_QUEUE_MAX_READS,
+};
+use transport::UsbTransport;
+use types::{BTag, Checked, CtlBTag, Handle, MessageSize, TermChar, Timeout, TransferSize};
 
-use anyhow::Result;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 
 /// Device filter
 pub trait DeviceFilter {
@@ -75,6 +119,11 @@ pub trait DeviceFilter {
         device: &rusb::Device<T>,
         device_desc: &DeviceDescriptor,
     ) -> bool;
+
+    /// The number of the USBTMC interface to use, `None` for the first one
+    fn interface_number(&self) -> Option<u8> {
+        None
+    }
 }
 
 /// ### UsbtmcClient
@@ -84,26 +133,76 @@ pub trait DeviceFilter {
 #[derive(Debug)]
 pub struct UsbtmcClient {
     handle: Handle,
-    mode: DeviceMode,
+    interface_number: u8,
     timeout: Timeout,
+    term_char: TermChar,
+    transfer_size: TransferSize,
+    message_size: MessageSize,
+    checked: Checked,
     capabilities: Capabilities,
     btag: BTag,
     ctl_btag: CtlBTag,
     endpoints: UsbtmcEndpoints,
+    listener: Mutex<Option<Arc<Listener>>>,
 }
 
 impl UsbtmcClient {
     /// ### TMC devices
     ///
-    /// Get a list of USB TMC devices
+    /// Get a list of USB TMC devices.
+    ///
+    /// Each device is displayed as its VISA resource string, which can be parsed back into
+    /// a [`VisaResource`] to connect to the device.
     ///
     pub fn devices() -> Result<Vec<DeviceInfo>> {
+        Self::list_devices().map_err(|e| e.with_context(ErrorContext::new("list devices")))
+    }
+
+    fn list_devices() -> Result<Vec<DeviceInfo>> {
         // setup context
         let mut context = rusb::Context::new()?;
 
         init::list_devices(&mut context)
     }
 
+    /// ### Pulse Device Indicator
+    ///
+    /// Make the activity indicator of a device blink, to physically locate it, without
+    /// connecting a client to it.
+    ///
+    /// The `filter` argument selects the device like in [`UsbtmcClient::connect`], for example
+    /// with a `DeviceInfo` returned by [`UsbtmcClient::devices`].
+    ///
+    pub fn pulse_device_indicator(filter: impl DeviceFilter) -> Result<()> {
+        Self::pulse_filtered_device_indicator(filter)
+            .map_err(|e| e.with_context(ErrorContext::new("INDICATOR_PULSE")))
+    }
+
+    fn pulse_filtered_device_indicator(filter: impl DeviceFilter) -> Result<()> {
+        // setup context
+        let mut context = rusb::Context::new()?;
+        // attempt to open the device
+        let interface_number = filter.interface_number();
+        let (device, mut handle) = init::open_device(&mut context, filter)?;
+
+        // only claim the interface for the duration of the request
+        let mut mode = init::get_usbtmc_mode(&device, interface_number)?;
+        init::detach_kernel_driver(&mut mode, &mut handle)?;
+        handle.claim_interface(mode.interface_number)?;
+
+        let interface_number = mode.interface_number;
+        let handle: Handle = Handle::new(UsbTransport::new(handle, mode));
+        let timeout: Timeout = Timeout::new(DEFAULT_TIMEOUT_DURATION);
+
+        // a device not accepting the request would stall it
+        let capabilities = control::get_capabilities(&handle, interface_number, &timeout)?;
+        if !capabilities.accepts_indicator_pulse_request {
+            return Err(Error::IndicatorPulseNotSupported);
+        }
+
+        control::indicator_pulse(&handle, interface_number, &timeout)
+    }
+
     /// ### Connect
     ///
     /// Connect a USB device and initialize it.
@@ -113,18 +212,27 @@ impl UsbtmcClient {
     /// - `(idVendor, idProduct)` or `DeviceId` - device by USB identifiers
     /// - `(bus, device)` or `DeviceAddr` - device by USB bus and device number
     /// - `DeviceInfo` - device by both USB identifiers and address
+    /// - `SerialNumber` - device by the serial number of its string descriptor, which
+    ///   unlike the address stays the same when the device is plugged again
+    /// - `VisaResource` - device by VISA resource string, such as
+    ///   `USB0::0x0957::0x1798::MY12345678::INSTR`
     ///
     pub fn connect(filter: impl DeviceFilter) -> Result<UsbtmcClient> {
+        Self::open(filter).map_err(|e| e.with_context(ErrorContext::new("connect")))
+    }
+
+    fn open(filter: impl DeviceFilter) -> Result<UsbtmcClient> {
         // setup context
         let mut context = rusb::Context::new()?;
         // attempt to open the device
+        let interface_number = filter.interface_number();
         let (device, mut handle) = init::open_device(&mut context, filter)?;
 
         // GET THE DEVICE MODE
         // ==========
 
         // get the mode
-        let mut mode = init::get_usbtmc_mode(&device)?;
+        let mut mode = init::get_usbtmc_mode(&device, interface_number)?;
         // detach kernel driver if it is used
         init::detach_kernel_driver(&mut mode, &mut handle)?;
 
@@ -138,21 +246,46 @@ impl UsbtmcClient {
         handle.claim_interface(mode.interface_number)?;
         handle.set_alternate_setting(mode.interface_number, mode.setting_number)?;
 
+        let interface_number = mode.interface_number;
+        UsbtmcClient::from_transport(UsbTransport::new(handle, mode), interface_number, endpoints)
+    }
+
+    /// ### From Transport
+    ///
+    /// Initialize a client over an already configured transport.
+    ///
+    /// This is the entry point to run the client against something else than a libusb device,
+    /// such as a [`MockTransport`].
+    ///
+    /// #### Arguments
+    /// - `transport` -> the transport to the device
+    /// - `interface_number` -> the number of the USBTMC interface
+    /// - `endpoints` -> the USBTMC endpoints of the interface
+    ///
+    pub fn from_transport(
+        transport: impl Transport + 'static,
+        interface_number: u8,
+        endpoints: UsbtmcEndpoints,
+    ) -> Result<UsbtmcClient> {
         // SETUP DATA FOR CLIENT
         // ==========
-        let handle: Handle = Handle::new(handle);
+        let handle: Handle = Handle::new(transport);
         let timeout: Timeout = Timeout::new(DEFAULT_TIMEOUT_DURATION);
+        let term_char: TermChar = TermChar::new(Some(DEFAULT_TERM_CHAR));
+        let transfer_size: TransferSize = TransferSize::new(DEFAULT_TRANSFER_SIZE);
+        let message_size: MessageSize = MessageSize::new(DEFAULT_MESSAGE_SIZE);
+        let checked: Checked = Checked::new(false);
         let btag = BTag::new();
         let ctl_btag = CtlBTag::new();
 
         // GET CAPABILITIES
         // ==========
         let capabilities: Capabilities =
-            control::get_capabilities(&handle, mode.interface_number, &timeout)?;
+            control::get_capabilities(&handle, interface_number, &timeout)?;
 
         // CLEAR THE BUFFERS AND FEATURES
         // ==========
-        control::clear_buffers(&handle, mode.interface_number, &timeout)?;
+        control::clear_buffers(&handle, interface_number, &endpoints.bulk_in_ep, &timeout)?;
         control::clear_feature(&handle, &endpoints.bulk_out_ep)?;
         control::clear_feature(&handle, &endpoints.bulk_in_ep)?;
 
@@ -160,15 +293,29 @@ impl UsbtmcClient {
         // ==========
         Ok(UsbtmcClient {
             handle,
-            mode,
+            interface_number,
             timeout,
+            term_char,
+            transfer_size,
+            message_size,
+            checked,
             capabilities,
             btag,
             ctl_btag,
             endpoints,
+            listener: Mutex::new(None),
         })
     }
 
+    /// ### Capabilities
+    ///
+    /// Get the capabilities the device reported on connection, including the ones of the
+    /// USB488 subclass.
+    ///
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
     /// ### Set Timeout
     ///
     /// Set a new timeout for the device connection.
@@ -176,10 +323,72 @@ impl UsbtmcClient {
     /// #### Arguments
     /// - `duration` -> the duration of the timeout
     ///
-    pub fn set_timeout(&self, duration: std::time::Duration) {
+    pub fn set_timeout(&self, duration: Duration) {
         *self.timeout.borrow() = duration;
     }
 
+    /// ### Set Term Char
+    ///
+    /// Set the termination character ending the reads from the device, or `None` to only
+    /// stop at the end of messages (for example when reading binary data).
+    ///
+    /// The termination character is only used if the device supports it.
+    /// Defaults to `\n`.
+    ///
+    /// #### Arguments
+    /// - `term_char` -> the termination character
+    ///
+    pub fn set_term_char(&self, term_char: Option<u8>) {
+        *self.term_char.borrow() = term_char;
+    }
+
+    /// ### Set Transfer Size
+    ///
+    /// Set the largest number of bytes requested from the device in a single transfer.
+    ///
+    /// Large transfers reduce the number of round trips needed to read long responses such
+    /// as waveforms. Defaults to 1 MiB.
+    ///
+    /// #### Arguments
+    /// - `transfer_size` -> the largest transfer size, at least 1
+    ///
+    pub fn set_transfer_size(&self, transfer_size: u32) {
+        *self.transfer_size.borrow() = transfer_size.max(1);
+    }
+
+    /// ### Set Message Size
+    ///
+    /// Set the largest payload sent to the device in a single message. Longer data is
+    /// split in several messages, the last one ending with the end of message flag.
+    ///
+    /// Defaults to 8 KiB.
+    ///
+    /// #### Arguments
+    /// - `message_size` -> the largest payload size, at least 1
+    ///
+    pub fn set_message_size(&self, message_size: u32) {
+        *self.message_size.borrow() = message_size.max(1);
+    }
+
+    /// ### Set Checked
+    ///
+    /// Enable or disable the checked mode. In checked mode, the status byte is read after
+    /// each command and query, and if the device flags an error its SCPI error queue is
+    /// drained with `SYST:ERR?`. The errors are returned as [`Error::Instrument`].
+    ///
+    /// Errors are flagged by the EAV bit of the status byte, or by the ESB bit when the
+    /// error bits are enabled with [`UsbtmcClient::set_event_status_enable`]. Devices
+    /// without the USB488 subclass are checked with `*ESR?` instead.
+    ///
+    /// Each command then costs an extra round trip to the device. Defaults to disabled.
+    ///
+    /// #### Arguments
+    /// - `checked` -> whether to check the error queue after commands
+    ///
+    pub fn set_checked(&self, checked: bool) {
+        *self.checked.borrow() = checked;
+    }
+
     /// ### Command
     ///
     /// Send a command to the device.
@@ -188,18 +397,87 @@ impl UsbtmcClient {
     /// - `cmd` -> the command to send
     ///
     pub fn command(&self, cmd: &str) -> Result<()> {
+        // Send the command
+        self.write_raw(cmd.as_bytes())
+            .map_err(Self::command_context("command", cmd))?;
+
+        self.check_errors(cmd)
+    }
+
+    /// ### Write Raw
+    ///
+    /// Send binary data to the device as a complete message.
+    ///
+    /// #### Arguments
+    /// - `data` -> the data to send
+    ///
+    pub fn write_raw(&self, data: &[u8]) -> Result<()> {
+        self.write_with_eom(data, true)
+    }
+
+    /// ### Write With EOM
+    ///
+    /// Send binary data to the device, ending the message only if `end_of_message` is set.
+    ///
+    /// A message can be sent in several parts by writing all but the last one with
+    /// `end_of_message` unset. Empty data sends nothing when `end_of_message` is unset, and
+    /// returns [`Error::EmptyMessage`] when it is set, as USBTMC forbids empty transfers.
+    ///
+    /// #### Arguments
+    /// - `data` -> the data to send
+    /// - `end_of_message` -> whether the data ends the message
+    ///
+    pub fn write_with_eom(&self, data: &[u8], end_of_message: bool) -> Result<()> {
         use communication::bulk;
 
-        // Send the command
         bulk::write(
             &self.handle,
             &self.btag,
-            cmd.into(),
+            data,
+            end_of_message,
+            *self.message_size.borrow(),
             &self.endpoints.bulk_out_ep,
             &self.timeout,
-        )?;
+        )
+        .map_err(|e| self.recover_out(e))
+        .map_err(|e| e.with_context(self.bulk_out_context("write")))
+    }
 
-        Ok(())
+    /// ### Write From
+    ///
+    /// Send `len` bytes read from `reader` to the device as a complete message.
+    ///
+    /// The data is streamed in messages of the configured message size, so large files
+    /// are never loaded in memory at once. If `reader` fails or ends before `len` bytes,
+    /// the message is left unfinished and the device should be cleared.
+    ///
+    /// #### Arguments
+    /// - `reader` -> the source of the data
+    /// - `len` -> the number of bytes to send
+    ///
+    pub fn write_from(&self, reader: impl std::io::Read, len: u64) -> Result<()> {
+        use communication::bulk;
+
+        bulk::write_from(
+            &self.handle,
+            &self.btag,
+            reader,
+            len,
+            *self.message_size.borrow(),
+            &self.endpoints.bulk_out_ep,
+            &self.timeout,
+        )
+        .map_err(|e| self.recover_out(e))
+        .map_err(|e| e.with_context(self.bulk_out_context("write")))
+    }
+
+    /// ### Stream
+    ///
+    /// Return a stream implementing [`std::io::Read`] and [`std::io::Write`] over the
+    /// bulk endpoints of the device.
+    ///
+    pub fn stream(&self) -> UsbtmcStream<'_> {
+        UsbtmcStream::new(self)
     }
 
     /// ### Query Raw
@@ -211,28 +489,216 @@ impl UsbtmcClient {
     /// - `cmd` -> the command to send
     ///
     pub fn query_raw(&self, cmd: &str) -> Result<Vec<u8>> {
-        use communication::bulk;
+        // Send a command
+        self.write_raw(cmd.as_bytes())
+            .map_err(Self::command_context("query", cmd))?;
 
+        // Read the response
+        let resp = self
+            .read_raw()
+            .map_err(Self::command_context("query", cmd))?;
+
+        self.check_errors(cmd)?;
+        Ok(resp)
+    }
+
+    /// ### Query With Term Char
+    ///
+    /// Send a command and get a response from the device, using the given termination
+    /// character instead of the one of the client.
+    ///
+    /// The response tells whether the read ended at the end of the message or on the
+    /// termination character.
+    ///
+    /// #### Arguments
+    /// - `cmd` -> the command to send
+    /// - `term_char` -> the termination character, or `None` to read until the end of the message
+    ///
+    pub fn query_with_term_char(&self, cmd: &str, term_char: Option<u8>) -> Result<Response> {
         // Send a command
-        bulk::write(
-            &self.handle,
-            &self.btag,
-            cmd.into(),
-            &self.endpoints.bulk_out_ep,
-            &self.timeout,
-        )?;
+        self.write_raw(cmd.as_bytes())
+            .map_err(Self::command_context("query", cmd))?;
+
+        // Read the response
+        let resp = self
+            .read_message(term_char, usize::MAX)
+            .map_err(Self::command_context("query", cmd))?;
+
+        self.check_errors(cmd)?;
+        Ok(resp)
+    }
+
+    /// ### Query Block
+    ///
+    /// Send a command and get the data of the IEEE 488.2 arbitrary block it returns.
+    ///
+    /// Both definite length (`#<n><length><data>`) and indefinite length (`#0<data>\n`)
+    /// blocks are supported. The response is read without termination character, across
+    /// several messages if needed, until the whole block is received.
+    ///
+    /// #### Arguments
+    /// - `cmd` -> the command to send
+    ///
+    pub fn query_block(&self, cmd: &str) -> Result<Vec<u8>> {
+        // Send a command
+        self.write_raw(cmd.as_bytes())
+            .map_err(Self::command_context("query", cmd))?;
+
+        // Read the response
+        let data = self
+            .read_block()
+            .map_err(Self::command_context("query", cmd))?;
+
+        self.check_errors(cmd)?;
+        Ok(data)
+    }
+
+    /// Read an IEEE 488.2 arbitrary block and return its data
+    fn read_block(&self) -> Result<Vec<u8>> {
+        use block::BlockLength;
+
+        // Read the header
+        let mut data: Vec<u8> = Vec::new();
+        let (header_size, length) = loop {
+            if let Some(header) = block::parse_header(&data)? {
+                break header;
+            }
+            if !self.read_block_message(&mut data)? {
+                return Err(Error::InvalidBlockHeader);
+            }
+        };
+
+        // Read the data
+        match length {
+            BlockLength::Definite(length) => {
+                let block_size = header_size + length;
+                while data.len() < block_size {
+                    if !self.read_block_message(&mut data)? {
+                        return Err(Error::IncompleteBlock {
+                            received: data.len().saturating_sub(header_size),
+                            expected: length,
+                        });
+                    }
+                }
+                data.truncate(block_size);
+            }
+            BlockLength::Indefinite => {
+                if data.last() == Some(&b'\n') {
+                    data.pop();
+                }
+            }
+        }
+        data.drain(..header_size);
+
+        Ok(data)
+    }
+
+    /// ### Query Binary Values
+    ///
+    /// Send a command and decode the IEEE 488.2 arbitrary block it returns into values,
+    /// for example the points of a waveform.
+    ///
+    /// #### Arguments
+    /// - `cmd` -> the command to send
+    /// - `byte_order` -> the order of the bytes of each value, as set on the device
+    ///
+    pub fn query_binary_values<T: BinaryValue>(
+        &self,
+        cmd: &str,
+        byte_order: ByteOrder,
+    ) -> Result<Vec<T>> {
+        let data = self.query_block(cmd)?;
+
+        block::decode(&data, byte_order).map_err(Self::command_context("query", cmd))
+    }
+
+    /// Append the next message of a block to `data`, return false if the message is empty
+    fn read_block_message(&self, data: &mut Vec<u8>) -> Result<bool> {
+        let resp = self.read_message(None, usize::MAX)?;
+        let received = !resp.data.is_empty();
+        match data.is_empty() {
+            true => *data = resp.data,
+            false => data.extend_from_slice(&resp.data),
+        }
+
+        Ok(received)
+    }
+
+    /// ### Query Into
+    ///
+    /// Send a command and read the response directly into `buf`.
+    ///
+    /// Return the number of bytes read and why the read ended. If the read ended because
+    /// `buf` is full, the rest of the response can be read with [`UsbtmcClient::read_into`].
+    ///
+    /// #### Arguments
+    /// - `cmd` -> the command to send
+    /// - `buf` -> the buffer receiving the response
+    ///
+    pub fn query_into(&self, cmd: &str, buf: &mut [u8]) -> Result<(usize, ReadEnd)> {
+        // Send a command
+        self.write_raw(cmd.as_bytes())
+            .map_err(Self::command_context("query", cmd))?;
 
         // Read the response
-        let resp = bulk::read(
+        let (len, end) = self
+            .read_into(buf)
+            .map_err(Self::command_context("query", cmd))?;
+
+        // the error queue can't be read before the rest of the response
+        if end != ReadEnd::TransferSize {
+            self.check_errors(cmd)?;
+        }
+        Ok((len, end))
+    }
+
+    /// ### Read Into
+    ///
+    /// Read a response from the device directly into `buf`.
+    ///
+    /// Return the number of bytes read and why the read ended.
+    ///
+    /// #### Arguments
+    /// - `buf` -> the buffer receiving the response
+    ///
+    pub fn read_into(&self, buf: &mut [u8]) -> Result<(usize, ReadEnd)> {
+        let term_char = *self.term_char.borrow();
+
+        self.read_into_with_term_char(buf, term_char)
+    }
+
+    /// ### Read Into With Term Char
+    ///
+    /// Read a response from the device directly into `buf`, using the given termination
+    /// character instead of the one of the client.
+    ///
+    /// Return the number of bytes read and why the read ended.
+    ///
+    /// #### Arguments
+    /// - `buf` -> the buffer receiving the response
+    /// - `term_char` -> the termination character, or `None` to read until the end of the message
+    ///
+    pub fn read_into_with_term_char(
+        &self,
+        buf: &mut [u8],
+        term_char: Option<u8>,
+    ) -> Result<(usize, ReadEnd)> {
+        use communication::bulk;
+
+        let term_char = self.device_term_char(term_char);
+
+        bulk::read_into(
             &self.handle,
             &self.btag,
             &self.endpoints.bulk_in_ep,
             &self.endpoints.bulk_out_ep,
-            &self.capabilities,
+            term_char,
+            *self.transfer_size.borrow(),
+            buf,
             &self.timeout,
-        )?;
-
-        Ok(resp)
+        )
+        .map_err(|e| self.recover_in(e))
+        .map_err(|e| e.with_context(self.bulk_in_context("read")))
     }
 
     /// ### Query
@@ -244,72 +710,641 @@ impl UsbtmcClient {
     /// - `cmd` -> the command to send
     ///
     pub fn query(&self, cmd: &str) -> Result<String> {
+        let resp = self.query_unchecked(cmd)?;
+
+        self.check_errors(cmd)?;
+        Ok(resp)
+    }
+
+    /// ### Read
+    ///
+    /// Get a response from the device without sending a command first, for example
+    /// after a trigger. The response is a utf-8 string.
+    ///
+    pub fn read(&self) -> Result<String> {
+        let resp = self.read_raw()?;
+
+        // filter out invalid bytes (not ASCII bytes and null bytes)
+        let resp: Vec<u8> = resp
+            .iter()
+            .filter(|v| v.is_ascii() && **v != 0x00)
+            .copied()
+            .collect();
+
+        // Convert response to string
+        let resp = std::str::from_utf8(&resp)
+            .map_err(|e| Error::from(e).with_context(self.bulk_in_context("read")))?
+            .trim();
+
+        Ok(String::from(resp))
+    }
+
+    /// ### Read Raw
+    ///
+    /// Get a response from the device without sending a command first.
+    /// The response is a vector of bytes.
+    ///
+    pub fn read_raw(&self) -> Result<Vec<u8>> {
+        let term_char = *self.term_char.borrow();
+        let resp = self.read_message(term_char, usize::MAX)?;
+
+        Ok(resp.data)
+    }
+
+    /// ### Read With Term Char
+    ///
+    /// Get a response from the device without sending a command first, using the given
+    /// termination character instead of the one of the client. For example, binary data is
+    /// read with `None`.
+    ///
+    /// The response tells whether the read ended at the end of the message or on the
+    /// termination character.
+    ///
+    /// #### Arguments
+    /// - `term_char` -> the termination character, or `None` to read until the end of the message
+    ///
+    pub fn read_with_term_char(&self, term_char: Option<u8>) -> Result<Response> {
+        self.read_message(term_char, usize::MAX)
+    }
+
+    /// ### Read Chunk
+    ///
+    /// Get at most `max_size` bytes of a response from the device without sending a command
+    /// first.
+    ///
+    /// The response tells whether the end of the message was reached. If it was not, the
+    /// rest of the message can be fetched with more reads.
+    ///
+    /// #### Arguments
+    /// - `max_size` -> the largest number of bytes to read
+    ///
+    pub fn read_chunk(&self, max_size: usize) -> Result<Response> {
+        let term_char = *self.term_char.borrow();
+
+        self.read_message(term_char, max_size)
+    }
+
+    /// Read a device dependent message from the device
+    fn read_message(&self, term_char: Option<u8>, max_size: usize) -> Result<Response> {
         use communication::bulk;
 
-        // Send a command
-        bulk::write(
+        let term_char = self.device_term_char(term_char);
+
+        bulk::read(
             &self.handle,
             &self.btag,
-            cmd.into(),
+            &self.endpoints.bulk_in_ep,
             &self.endpoints.bulk_out_ep,
+            term_char,
+            *self.transfer_size.borrow(),
+            max_size,
             &self.timeout,
-        )?;
+        )
+        .map_err(|e| self.recover_in(e))
+        .map_err(|e| e.with_context(self.bulk_in_context("read")))
+    }
 
-        // Read the response
-        let resp = bulk::read(
+    /// The termination character to request, the device can only stop on one if it supports it
+    fn device_term_char(&self, term_char: Option<u8>) -> Option<u8> {
+        term_char.filter(|_| self.capabilities.supports_bulk_in_term_char)
+    }
+
+    /// The context of an error on the BULK OUT endpoint
+    fn bulk_out_context(&self, operation: &'static str) -> ErrorContext {
+        ErrorContext::new(operation)
+            .btag(self.btag.last())
+            .endpoint(self.endpoints.bulk_out_ep.address)
+    }
+
+    /// The context of an error on the BULK IN endpoint
+    fn bulk_in_context(&self, operation: &'static str) -> ErrorContext {
+        ErrorContext::new(operation)
+            .btag(self.btag.last())
+            .endpoint(self.endpoints.bulk_in_ep.address)
+    }
+
+    /// The context of an error while sending `cmd`
+    fn command_context(operation: &'static str, cmd: &str) -> impl FnOnce(Error) -> Error {
+        let context = ErrorContext::new(operation).command(cmd);
+        move |e| e.with_context(context)
+    }
+
+    /// ### Vendor Write
+    ///
+    /// Send vendor specific data to the device.
+    ///
+    /// #### Arguments
+    /// - `data` -> the data to send
+    ///
+    pub fn vendor_write(&self, data: &[u8]) -> Result<()> {
+        use communication::bulk;
+
+        bulk::vendor_write(
+            &self.handle,
+            &self.btag,
+            data,
+            *self.message_size.borrow(),
+            &self.endpoints.bulk_out_ep,
+            &self.timeout,
+        )
+        .map_err(|e| self.recover_out(e))
+        .map_err(|e| e.with_context(self.bulk_out_context("vendor write")))
+    }
+
+    /// ### Vendor Read
+    ///
+    /// Read vendor specific data from the device.
+    ///
+    pub fn vendor_read(&self) -> Result<Vec<u8>> {
+        use communication::bulk;
+
+        bulk::vendor_read(
             &self.handle,
             &self.btag,
             &self.endpoints.bulk_in_ep,
             &self.endpoints.bulk_out_ep,
-            &self.capabilities,
+            *self.transfer_size.borrow(),
             &self.timeout,
-        )?;
+        )
+        .map_err(|e| self.recover_in(e))
+        .map_err(|e| e.with_context(self.bulk_in_context("vendor read")))
+    }
 
-        // filter out invalid bytes (not ASCII bytes and null bytes)
-        let resp: Vec<u8> = resp
-            .iter()
-            .filter(|v| v.is_ascii() && **v != 0x00)
-            .map(|v| *v)
-            .collect();
+    /// ### Vendor Query
+    ///
+    /// Send vendor specific data and read the vendor specific response of the device.
+    ///
+    /// #### Arguments
+    /// - `data` -> the data to send
+    ///
+    pub fn vendor_query(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.vendor_write(data)?;
+        self.vendor_read()
+    }
 
-        // Convert response to string
-        let resp = std::str::from_utf8(&resp)?.trim();
+    /// ### Abort Out
+    ///
+    /// Abort the last transfer sent to the BULK OUT endpoint, and clear the halt of the endpoint.
+    ///
+    /// This is done automatically when writing to the device fails.
+    ///
+    /// #### Returns
+    /// Returns the number of bytes the device received before aborting the transfer
+    ///
+    pub fn abort_out(&self) -> Result<usize> {
+        control::abort_bulk_out_transfer(
+            &self.handle,
+            &self.endpoints.bulk_out_ep,
+            self.btag.last(),
+            &self.timeout,
+        )
+        .map_err(|e| e.with_context(self.bulk_out_context("abort")))
+    }
 
-        Ok(String::from(resp))
+    /// ### Abort In
+    ///
+    /// Abort the last transfer requested from the BULK IN endpoint, discard the data left
+    /// in the device output buffer, and clear the halt of the endpoint.
+    ///
+    /// This is done automatically when reading from the device fails.
+    ///
+    /// #### Returns
+    /// Returns the number of bytes the device sent before aborting the transfer
+    ///
+    pub fn abort_in(&self) -> Result<usize> {
+        control::abort_bulk_in_transfer(
+            &self.handle,
+            &self.endpoints.bulk_in_ep,
+            self.btag.last(),
+            &self.timeout,
+        )
+        .map_err(|e| e.with_context(self.bulk_in_context("abort")))
+    }
+
+    /// ### Clear
+    ///
+    /// Clear the device, like a VISA `viClear`, without closing the connection.
+    ///
+    /// The transfers in progress are aborted, the input and output buffers of the device are
+    /// cleared, and the halts of the bulk endpoints are cleared.
+    ///
+    pub fn clear(&self) -> Result<()> {
+        // ABORT THE TRANSFERS
+        // ==========
+        // the device reports a failure when there is no transfer to abort
+        let ignore_idle = |result: Result<usize>| match result {
+            Ok(_) => Ok(()),
+            Err(e) => match e.without_context() {
+                Error::StatusFailure | Error::StatusNoTransferInProgress => Ok(()),
+                _ => Err(e),
+            },
+        };
+        ignore_idle(self.abort_out())?;
+        ignore_idle(self.abort_in())?;
+
+        // CLEAR THE BUFFERS AND FEATURES
+        // ==========
+        let clear_context = || ErrorContext::new("clear");
+        control::clear_buffers(
+            &self.handle,
+            self.interface_number,
+            &self.endpoints.bulk_in_ep,
+            &self.timeout,
+        )
+        .map_err(|e| e.with_context(clear_context()))?;
+        control::clear_feature(&self.handle, &self.endpoints.bulk_out_ep)
+            .map_err(|e| e.with_context(clear_context()))?;
+        control::clear_feature(&self.handle, &self.endpoints.bulk_in_ep)
+            .map_err(|e| e.with_context(clear_context()))?;
+
+        Ok(())
+    }
+
+    /// Abort the BULK OUT transfer which failed with `error`
+    fn recover_out(&self, error: Error) -> Error {
+        if !matches!(error, Error::Timeout | Error::Usb(_)) {
+            return error;
+        }
+
+        match self.abort_out() {
+            Ok(_) => Error::BulkOutTransferAborted(Box::new(error)),
+            // the device had already discarded the transfer, only the halt is left to clear
+            Err(e) if matches!(e.without_context(), Error::StatusFailure) => {
+                match control::clear_feature(&self.handle, &self.endpoints.bulk_out_ep) {
+                    Ok(_) => Error::BulkOutTransferAborted(Box::new(error)),
+                    Err(e) => e,
+                }
+            }
+            Err(e) => e,
+        }
+    }
+
+    /// Abort the BULK IN transfer which failed with `error`
+    fn recover_in(&self, error: Error) -> Error {
+        match error {
+            // the device sent a malformed transfer, discard whatever it has left to send
+            Error::BulkInShortHeader
+            | Error::BulkInUnexpectedMsgId(_)
+            | Error::BulkInMismatchedBTag { .. }
+            | Error::BulkInTransferSizeExceeded { .. }
+            | Error::BulkInShortTransfer { .. } => {
+                if let Err(e) = self.abort_in() {
+                    if matches!(e.without_context(), Error::StatusFailure) {
+                        let _ = control::clear_feature(&self.handle, &self.endpoints.bulk_in_ep);
+                    }
+                }
+                return error;
+            }
+            Error::Timeout | Error::Usb(_) => {}
+            _ => return error,
+        }
+
+        match self.abort_in() {
+            Ok(_) => Error::BulkInTransferAborted(Box::new(error)),
+            // the device had nothing left to send, only the halt is left to clear
+            Err(e) if matches!(e.without_context(), Error::StatusFailure) => {
+                match control::clear_feature(&self.handle, &self.endpoints.bulk_in_ep) {
+                    Ok(_) => Error::BulkInTransferAborted(Box::new(error)),
+                    Err(e) => e,
+                }
+            }
+            Err(e) => e,
+        }
     }
 
     /// ### Read IEEE 488 Status Byte
-    /// 
+    ///
     /// The IEEE 488 status byte is read directly from the control endpoint
     /// instead of going through the BULK IN endpoint.
-    /// 
-    pub fn read_ieee488_status_byte(&self) -> Result<u8> {
+    ///
+    pub fn read_ieee488_status_byte(&self) -> Result<StatusByte> {
+        let listener: Option<Arc<Listener>> = self.listener.lock().unwrap().clone();
         let ieee488_byte = control::read_status_byte(
             &self.handle,
-            self.mode.interface_number,
+            self.interface_number,
             &self.ctl_btag,
             &self.endpoints.interrupt_ep,
+            listener.as_deref(),
             &self.timeout,
-        )?;
+        )
+        .map_err(|e| e.with_context(ErrorContext::new("READ_STATUS_BYTE")))?;
 
         Ok(ieee488_byte)
     }
-}
 
-impl Drop for UsbtmcClient {
-    fn drop(&mut self) {
-        // RESET THE CONFIGURATION
-        // Release the interface
-        self.handle
-            .borrow()
-            .release_interface(self.mode.interface_number)
-            .expect("failed to release device usb interface");
-        // Reattach the kernel driver if it was disconnected
-        if self.mode.has_kernel_driver {
-            self.handle
-                .borrow()
-                .attach_kernel_driver(self.mode.interface_number)
-                .expect("failed to attach kernel driver to usb device");
+    /// ### Query Status Byte
+    ///
+    /// Read the status byte with `*STB?`. Bit 6 is the master summary status instead of the
+    /// request service bit.
+    ///
+    pub fn query_status_byte(&self) -> Result<StatusByte> {
+        Ok(StatusByte::from(self.query_register("*STB?")? as u8))
+    }
+
+    /// ### Set Service Request Enable
+    ///
+    /// Set which bits of the status byte request service, with `*SRE`.
+    ///
+    /// #### Arguments
+    /// - `mask` -> the status byte bits requesting service
+    ///
+    pub fn set_service_request_enable(&self, mask: StatusByte) -> Result<()> {
+        self.command(&format!("*SRE {}", mask.bits()))
+    }
+
+    /// ### Read Event Status
+    ///
+    /// Read and clear the standard event status register, with `*ESR?`.
+    ///
+    pub fn read_event_status(&self) -> Result<EventStatus> {
+        Ok(EventStatus::from(self.query_register("*ESR?")? as u8))
+    }
+
+    /// ### Set Event Status Enable
+    ///
+    /// Set which bits of the standard event status register are summarized in the ESB bit
+    /// of the status byte, with `*ESE`.
+    ///
+    /// #### Arguments
+    /// - `mask` -> the event status bits to summarize
+    ///
+    pub fn set_event_status_enable(&self, mask: EventStatus) -> Result<()> {
+        self.command(&format!("*ESE {}", mask.bits()))
+    }
+
+    /// ### Read Operation Condition
+    ///
+    /// Read the SCPI operation status condition register, with `STAT:OPER:COND?`.
+    ///
+    pub fn read_operation_condition(&self) -> Result<OperationStatus> {
+        Ok(OperationStatus::from(
+            self.query_register("STAT:OPER:COND?")?,
+        ))
+    }
+
+    /// ### Read Operation Event
+    ///
+    /// Read and clear the SCPI operation status event register, with `STAT:OPER:EVEN?`.
+    ///
+    pub fn read_operation_event(&self) -> Result<OperationStatus> {
+        Ok(OperationStatus::from(
+            self.query_register("STAT:OPER:EVEN?")?,
+        ))
+    }
+
+    /// ### Read Questionable Condition
+    ///
+    /// Read the SCPI questionable status condition register, with `STAT:QUES:COND?`.
+    ///
+    pub fn read_questionable_condition(&self) -> Result<QuestionableStatus> {
+        Ok(QuestionableStatus::from(
+            self.query_register("STAT:QUES:COND?")?,
+        ))
+    }
+
+    /// ### Read Questionable Event
+    ///
+    /// Read and clear the SCPI questionable status event register, with `STAT:QUES:EVEN?`.
+    ///
+    pub fn read_questionable_event(&self) -> Result<QuestionableStatus> {
+        Ok(QuestionableStatus::from(
+            self.query_register("STAT:QUES:EVEN?")?,
+        ))
+    }
+
+    /// Query a status register returned as a decimal number, such as `+32`
+    fn query_register(&self, cmd: &str) -> Result<u16> {
+        let resp = self.query_unchecked(cmd)?;
+
+        resp.trim_start_matches('+')
+            .parse::<u16>()
+            .map_err(|_| Error::InvalidResponse(resp.clone()))
+            .map_err(Self::command_context("query", cmd))
+    }
+
+    /// ### Read Errors
+    ///
+    /// Drain the SCPI error queue of the device with `SYST:ERR?`, until it answers with
+    /// `0,"No error"`.
+    ///
+    pub fn read_errors(&self) -> Result<Vec<InstrumentError>> {
+        let mut errors: Vec<InstrumentError> = Vec::new();
+        for _ in 0..ERROR_QUEUE_MAX_READS {
+            let resp = self.query_unchecked("SYST:ERR?")?;
+            let error = InstrumentError::parse(&resp)
+                .ok_or_else(|| Error::InvalidResponse(resp.clone()))
+                .map_err(Self::command_context("query", "SYST:ERR?"))?;
+            if error.code == 0 {
+                break;
+            }
+            errors.push(error);
+        }
+
+        Ok(errors)
+    }
+
+    /// In checked mode, return the errors the device reported after `cmd`
+    fn check_errors(&self, cmd: &str) -> Result<()> {
+        if !*self.checked.borrow() || !self.error_flagged()? {
+            return Ok(());
+        }
+
+        let mut errors = self.read_errors()?;
+        if errors.is_empty() {
+            return Ok(());
+        }
+        for error in errors.iter_mut() {
+            error.command = Some(cmd.to_string());
+        }
+
+        Err(Error::Instrument(errors))
+    }
+
+    /// Whether the status of the device flags an error
+    fn error_flagged(&self) -> Result<bool> {
+        // without USB488 the status byte can only be read with a query
+        if self.capabilities.usb488.bcd_version == 0 {
+            return Ok(self.read_event_status()?.has_error());
+        }
+
+        let status_byte = self.read_ieee488_status_byte()?;
+        if status_byte.error_available() {
+            return Ok(true);
+        }
+        match status_byte.event_status() {
+            true => Ok(self.read_event_status()?.has_error()),
+            false => Ok(false),
+        }
+    }
+
+    /// Send a query without checking the error queue afterwards
+    fn query_unchecked(&self, cmd: &str) -> Result<String> {
+        // Send a command
+        self.write_raw(cmd.as_bytes())
+            .map_err(Self::command_context("query", cmd))?;
+
+        // Read the response
+        self.read().map_err(Self::command_context("query", cmd))
+    }
+
+    /// ### On SRQ
+    ///
+    /// Register a function called with the status byte of each service request (SRQ) posted
+    /// by the device.
+    ///
+    /// The function is called from a thread dispatching the service requests received by the
+    /// thread listening to the INTERRUPT IN endpoint, which is started if it isn't already
+    /// running. The function may make requests to the device, such as reading the status byte.
+    ///
+    pub fn on_srq(&self, callback: impl Fn(StatusByte) + Send + Sync + 'static) -> Result<()> {
+        self.srq_listener()?.on_srq(callback);
+        Ok(())
+    }
+
+    /// ### SRQ Receiver
+    ///
+    /// Get a channel receiving the status byte of each service request (SRQ) posted by the device.
+    ///
+    /// The thread listening to the INTERRUPT IN endpoint is started if it isn't already running.
+    ///
+    pub fn srq_receiver(&self) -> Result<mpsc::Receiver<StatusByte>> {
+        Ok(self.srq_listener()?.subscribe())
+    }
+
+    /// ### Wait For SRQ
+    ///
+    /// Block until the device posts a service request (SRQ) and return its status byte.
+    /// A service request received since the last wait is returned immediately.
+    ///
+    /// Returns `None` if no service request was posted before the timeout.
+    ///
+    /// The thread listening to the INTERRUPT IN endpoint is started if it isn't already running.
+    ///
+    /// #### Arguments
+    /// - `timeout` -> how long to wait for the service request
+    ///
+    pub fn wait_for_srq(&self, timeout: Duration) -> Result<Option<StatusByte>> {
+        self.srq_listener()?
+            .wait_for_srq(timeout)
+            .map_err(|e| e.with_context(self.interrupt_context("wait for SRQ")))
+    }
+
+    /// Get the listener of the INTERRUPT IN endpoint, starting it if needed
+    fn srq_listener(&self) -> Result<Arc<Listener>> {
+        let interrupt_ep = match &self.endpoints.interrupt_ep {
+            Some(ep) => ep,
+            None => {
+                return Err(Error::InterruptEndpointNotFound
+                    .with_context(self.interrupt_context("start SRQ listener")))
+            }
         };
+
+        let mut listener = self.listener.lock().unwrap();
+        if listener.is_none() {
+            let started = Listener::start(&self.handle, interrupt_ep)
+                .map_err(|e| e.with_context(self.interrupt_context("start SRQ listener")))?;
+            *listener = Some(Arc::new(started));
+        }
+
+        Ok(listener.clone().unwrap())
+    }
+
+    /// The context of an error on the INTERRUPT IN endpoint
+    fn interrupt_context(&self, operation: &'static str) -> ErrorContext {
+        match &self.endpoints.interrupt_ep {
+            Some(ep) => ErrorContext::new(operation).endpoint(ep.address),
+            None => ErrorContext::new(operation),
+        }
+    }
+
+    /// ### Pulse Indicator
+    ///
+    /// Make the activity indicator of the device blink, to physically locate it.
+    ///
+    /// Requires the device to accept the INDICATOR_PULSE request.
+    ///
+    pub fn pulse_indicator(&self) -> Result<()> {
+        if !self.capabilities.accepts_indicator_pulse_request {
+            return Err(Error::IndicatorPulseNotSupported
+                .with_context(ErrorContext::new("INDICATOR_PULSE")));
+        }
+        control::indicator_pulse(&self.handle, self.interface_number, &self.timeout)
+            .map_err(|e| e.with_context(ErrorContext::new("INDICATOR_PULSE")))
+    }
+
+    /// ### Trigger
+    ///
+    /// Trigger the device.
+    ///
+    /// When the interface accepts it, the USB488 TRIGGER message is sent, the USB equivalent
+    /// of the GPIB Group Execute Trigger. Otherwise, the `*TRG` command is sent.
+    ///
+    pub fn trigger(&self) -> Result<()> {
+        use communication::bulk;
+
+        if !self.capabilities.usb488.accepts_trigger {
+            return self.command("*TRG");
+        }
+
+        bulk::trigger(
+            &self.handle,
+            &self.btag,
+            &self.endpoints.bulk_out_ep,
+            &self.timeout,
+        )
+        .map_err(|e| self.recover_out(e))
+        .map_err(|e| e.with_context(self.bulk_out_context("trigger")))
+    }
+
+    /// ### REN Control
+    ///
+    /// Assert or deassert the Remote Enable (REN) line. With REN asserted, the device goes to
+    /// remote when it is next addressed.
+    ///
+    /// Requires the device to accept the USB488 remote/local requests.
+    ///
+    /// #### Arguments
+    /// - `enable` -> whether to assert REN
+    ///
+    pub fn ren_control(&self, enable: bool) -> Result<()> {
+        self.check_remote_local_support()
+            .and_then(|_| {
+                control::ren_control(&self.handle, self.interface_number, enable, &self.timeout)
+            })
+            .map_err(|e| e.with_context(ErrorContext::new("REN_CONTROL")))
+    }
+
+    /// ### Go To Local
+    ///
+    /// Return the device to local control.
+    ///
+    /// Requires the device to accept the USB488 remote/local requests.
+    ///
+    pub fn go_to_local(&self) -> Result<()> {
+        self.check_remote_local_support()
+            .and_then(|_| control::go_to_local(&self.handle, self.interface_number, &self.timeout))
+            .map_err(|e| e.with_context(ErrorContext::new("GO_TO_LOCAL")))
+    }
+
+    /// ### Local Lockout
+    ///
+    /// Disable the front panel controls of the device, so it can't be returned to local by
+    /// an operator.
+    ///
+    /// Requires the device to accept the USB488 remote/local requests.
+    ///
+    pub fn local_lockout(&self) -> Result<()> {
+        self.check_remote_local_support()
+            .and_then(|_| {
+                control::local_lockout(&self.handle, self.interface_number, &self.timeout)
+            })
+            .map_err(|e| e.with_context(ErrorContext::new("LOCAL_LOCKOUT")))
+    }
+
+    fn check_remote_local_support(&self) -> Result<()> {
+        if !self.capabilities.usb488.accepts_remote_local_requests {
+            return Err(Error::RemoteLocalNotSupported);
+        }
+        Ok(())
     }
 }
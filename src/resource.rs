@@ -0,0 +1,109 @@
+//! ## Resource
+//!
+//! VISA resource strings of USB instruments, as used by NI-VISA, PyVISA and LabVIEW.
+//!
+//! A USB resource string is `USB[board]::<idVendor>::<idProduct>::<serial>[::<interface>][::INSTR]`,
+//! for example `USB0::0x0957::0x1798::MY12345678::INSTR`. The identifiers are decimal or
+//! hexadecimal with a `0x` prefix.
+//!
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Error;
+
+/// ### VISA Resource
+///
+/// The VISA resource string of a USB instrument.
+///
+/// Parse one with [`str::parse`] and pass it to [`crate::UsbtmcClient::connect`] to select
+/// the device, or print one with its `Display` implementation.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VisaResource {
+    /// The VISA board number, not used to select the device
+    pub board: u16,
+    /// USB Id Vendor
+    pub vendor_id: u16,
+    /// USB Id Product
+    pub product_id: u16,
+    /// The serial number of the device, `None` to match any serial number
+    pub serial_number: Option<String>,
+    /// The number of the USBTMC interface, `None` for the first one. It is only written
+    /// along with the serial number.
+    pub interface_number: Option<u8>,
+}
+
+impl FromStr for VisaResource {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<VisaResource, Error> {
+        let invalid = || Error::InvalidResource(s.to_string());
+
+        let mut parts: Vec<&str> = s.trim().split("::").collect();
+        if parts
+            .last()
+            .is_some_and(|class| class.eq_ignore_ascii_case("INSTR"))
+        {
+            parts.pop();
+        }
+        if !(3..=5).contains(&parts.len()) {
+            return Err(invalid());
+        }
+
+        // the interface type and board number
+        let board = match parts[0].get(..3) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("USB") => match &parts[0][3..] {
+                "" => 0,
+                board => board.parse().map_err(|_| invalid())?,
+            },
+            _ => return Err(invalid()),
+        };
+
+        let vendor_id = parse_id(parts[1]).ok_or_else(invalid)?;
+        let product_id = parse_id(parts[2]).ok_or_else(invalid)?;
+        let serial_number = match parts.get(3) {
+            Some(&"") => return Err(invalid()),
+            Some(serial) => Some(serial.to_string()),
+            None => None,
+        };
+        let interface_number = match parts.get(4) {
+            Some(number) => Some(number.parse().map_err(|_| invalid())?),
+            None => None,
+        };
+
+        Ok(VisaResource {
+            board,
+            vendor_id,
+            product_id,
+            serial_number,
+            interface_number,
+        })
+    }
+}
+
+impl fmt::Display for VisaResource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "USB{}::0x{:04X}::0x{:04X}",
+            self.board, self.vendor_id, self.product_id
+        )?;
+        if let Some(serial_number) = &self.serial_number {
+            write!(f, "::{}", serial_number)?;
+            if let Some(interface_number) = self.interface_number {
+                write!(f, "::{}", interface_number)?;
+            }
+        }
+
+        write!(f, "::INSTR")
+    }
+}
+
+/// Parse a decimal or `0x` prefixed hexadecimal identifier
+fn parse_id(id: &str) -> Option<u16> {
+    match id.get(..2) {
+        Some("0x" | "0X") => u16::from_str_radix(&id[2..], 16).ok(),
+        _ => id.parse().ok(),
+    }
+}
@@ -0,0 +1,718 @@
+//! ## Simulator
+//!
+//! An in-process USBTMC/USB488 device, to run the client against something that speaks the protocol.
+//!
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+use rusb::{Direction, TransferType};
+
+use crate::constants::{bulk_msg_id, control_requests, misc, usbtmc_status};
+use crate::transport::Transport;
+use crate::types::{Endpoint, UsbtmcEndpoints};
+
+/// Address of the simulated BULK OUT endpoint
+const BULK_OUT_ADDRESS: u8 = 0x01;
+/// Address of the simulated BULK IN endpoint
+const BULK_IN_ADDRESS: u8 = 0x82;
+/// Address of the simulated INTERRUPT IN endpoint
+const INTERRUPT_IN_ADDRESS: u8 = 0x83;
+
+/// A function producing the response to a query
+type QueryHandler = Box<dyn FnMut(&str) -> Vec<u8> + Send>;
+/// A function producing the response to vendor specific data
+type VendorHandler = Box<dyn FnMut(&[u8]) -> Vec<u8> + Send>;
+
+/// ### Simulated Device
+///
+/// A USBTMC/USB488 device living in memory.
+///
+/// The device parses the bulk messages sent by the host, answers the USBTMC control requests
+/// and replies to the queries registered with [`SimulatedDevice::on_query`]. Messages without
+/// a registered handler are accepted as commands and recorded.
+///
+/// The device is cheap to clone and all clones share the same state, so one copy can be given
+/// to the client while the test keeps the other.
+///
+/// ```
+/// use rs_usbtmc::{SimulatedDevice, UsbtmcClient};
+///
+/// let device = SimulatedDevice::new();
+/// device.on_query("*IDN?", |_| b"SIM,USBTMC,0,1.0\n".to_vec());
+///
+/// let client = UsbtmcClient::from_transport(device.clone(), 0, device.endpoints()).unwrap();
+/// assert_eq!(client.query("*IDN?").unwrap(), "SIM,USBTMC,0,1.0");
+/// ```
+///
+#[derive(Clone)]
+pub struct SimulatedDevice(Arc<(Mutex<SimulatorState>, Condvar)>);
+
+impl std::fmt::Debug for SimulatedDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SimulatedDevice").finish_non_exhaustive()
+    }
+}
+
+/// A DEV_DEP_MSG_OUT or VENDOR_SPECIFIC_OUT transfer which is still being received
+struct OutTransfer {
+    btag: u8,
+    remaining: usize,
+    end_of_message: bool,
+    vendor_specific: bool,
+}
+
+/// A REQUEST_DEV_DEP_MSG_IN waiting for data to send back
+struct InRequest {
+    btag: u8,
+    transfer_size: usize,
+    term_char: Option<u8>,
+}
+
+struct SimulatorState {
+    max_packet_size: u16,
+    has_interrupt_endpoint: bool,
+    interface_capabilities: u8,
+    device_capabilities: u8,
+    usb488_interface_capabilities: u8,
+    usb488_device_capabilities: u8,
+    status_byte: u8,
+    triggers: usize,
+    handlers: Vec<(String, QueryHandler)>,
+    vendor_handler: Option<VendorHandler>,
+    // BULK OUT
+    out_transfer: Option<OutTransfer>,
+    message: Vec<u8>,
+    last_out_btag: u8,
+    bytes_received: usize,
+    vendor_transfer: Vec<u8>,
+    // BULK IN
+    responses: VecDeque<Vec<u8>>,
+    vendor_responses: VecDeque<u8>,
+    in_request: Option<InRequest>,
+    in_transfers: VecDeque<Vec<u8>>,
+    last_in_btag: u8,
+    bytes_transfered: usize,
+    // INTERRUPT IN
+    interrupt_in: VecDeque<Vec<u8>>,
+    // LOGS
+    transfers: Vec<Vec<u8>>,
+    messages: Vec<Vec<u8>>,
+    vendor_messages: Vec<Vec<u8>>,
+    control_requests: Vec<u8>,
+    cleared_halts: Vec<u8>,
+}
+
+impl Default for SimulatedDevice {
+    fn default() -> SimulatedDevice {
+        SimulatedDevice::new()
+    }
+}
+
+impl SimulatedDevice {
+    /// ### New
+    ///
+    /// Return a USB488 device with 64 bytes packets, no INTERRUPT IN endpoint and support for
+    /// the bulk in termination character.
+    ///
+    pub fn new() -> SimulatedDevice {
+        SimulatedDevice(Arc::new((
+            Mutex::new(SimulatorState {
+                max_packet_size: 64,
+                has_interrupt_endpoint: false,
+                interface_capabilities: 0b0000_0100,
+                device_capabilities: 0b0000_0001,
+                usb488_interface_capabilities: 0b0000_0000,
+                usb488_device_capabilities: 0b0000_0000,
+                status_byte: 0x00,
+                triggers: 0,
+                handlers: Vec::new(),
+                vendor_handler: None,
+                out_transfer: None,
+                message: Vec::new(),
+                last_out_btag: 0,
+                bytes_received: 0,
+                vendor_transfer: Vec::new(),
+                responses: VecDeque::new(),
+                vendor_responses: VecDeque::new(),
+                in_request: None,
+                in_transfers: VecDeque::new(),
+                last_in_btag: 0,
+                bytes_transfered: 0,
+                interrupt_in: VecDeque::new(),
+                transfers: Vec::new(),
+                messages: Vec::new(),
+                vendor_messages: Vec::new(),
+                control_requests: Vec::new(),
+                cleared_halts: Vec::new(),
+            }),
+            Condvar::new(),
+        )))
+    }
+
+    fn state(&self) -> MutexGuard<'_, SimulatorState> {
+        self.0 .0.lock().unwrap()
+    }
+
+    /// Wake up the readers waiting on the INTERRUPT IN endpoint
+    fn notify_interrupt(&self) {
+        self.0 .1.notify_all();
+    }
+
+    /// ### Endpoints
+    ///
+    /// Return the endpoints of the device, to give to [`crate::UsbtmcClient::from_transport`].
+    ///
+    pub fn endpoints(&self) -> UsbtmcEndpoints {
+        let state = self.state();
+        UsbtmcEndpoints {
+            bulk_out_ep: Endpoint {
+                address: BULK_OUT_ADDRESS,
+                max_packet_size: state.max_packet_size,
+                transfer_type: TransferType::Bulk,
+                direction: Direction::Out,
+            },
+            bulk_in_ep: Endpoint {
+                address: BULK_IN_ADDRESS,
+                max_packet_size: state.max_packet_size,
+                transfer_type: TransferType::Bulk,
+                direction: Direction::In,
+            },
+            interrupt_ep: match state.has_interrupt_endpoint {
+                true => Some(Endpoint {
+                    address: INTERRUPT_IN_ADDRESS,
+                    max_packet_size: 2,
+                    transfer_type: TransferType::Interrupt,
+                    direction: Direction::In,
+                }),
+                false => None,
+            },
+        }
+    }
+
+    /// ### Set Max Packet Size
+    ///
+    /// Set the max packet size of the bulk endpoints.
+    ///
+    pub fn set_max_packet_size(&self, max_packet_size: u16) {
+        self.state().max_packet_size = max_packet_size;
+    }
+
+    /// ### Set Interrupt Endpoint
+    ///
+    /// Set whether the device has an INTERRUPT IN endpoint.
+    ///
+    pub fn set_interrupt_endpoint(&self, has_interrupt_endpoint: bool) {
+        self.state().has_interrupt_endpoint = has_interrupt_endpoint;
+    }
+
+    /// ### Set Capabilities
+    ///
+    /// Set the USBTMC interface and device capability bytes returned by GET_CAPABILITIES.
+    ///
+    pub fn set_capabilities(&self, interface_capabilities: u8, device_capabilities: u8) {
+        let mut state = self.state();
+        state.interface_capabilities = interface_capabilities;
+        state.device_capabilities = device_capabilities;
+    }
+
+    /// ### Set USB488 Capabilities
+    ///
+    /// Set the USB488 interface and device capability bytes returned by GET_CAPABILITIES.
+    ///
+    pub fn set_usb488_capabilities(&self, interface_capabilities: u8, device_capabilities: u8) {
+        let mut state = self.state();
+        state.usb488_interface_capabilities = interface_capabilities;
+        state.usb488_device_capabilities = device_capabilities;
+    }
+
+    /// ### Set Status Byte
+    ///
+    /// Set the IEEE 488 status byte. The MAV bit is managed by the device.
+    ///
+    pub fn set_status_byte(&self, status_byte: u8) {
+        self.state().status_byte = status_byte & !0b0001_0000;
+    }
+
+    /// ### Request Service
+    ///
+    /// Set the status byte with the RQS bit and post a SRQ notification on the INTERRUPT IN
+    /// endpoint, if the device has one.
+    ///
+    pub fn request_service(&self, status_byte: u8) {
+        let mut state = self.state();
+        state.status_byte = (status_byte | 0b0100_0000) & !0b0001_0000;
+        if state.has_interrupt_endpoint {
+            let status_byte = state.status_byte();
+            state.interrupt_in.push_back(vec![0x81, status_byte]);
+        }
+        drop(state);
+        self.notify_interrupt();
+    }
+
+    /// ### On Query
+    ///
+    /// Register the handler answering `query`.
+    ///
+    /// The query is matched against the received messages stripped of surrounding whitespace,
+    /// ignoring ASCII case. The handler gets the full message and returns the response.
+    ///
+    pub fn on_query(&self, query: &str, handler: impl FnMut(&str) -> Vec<u8> + Send + 'static) {
+        self.state()
+            .handlers
+            .push((query.trim().to_string(), Box::new(handler)));
+    }
+
+    /// ### On Vendor
+    ///
+    /// Register the handler answering vendor specific data. The handler gets the data of each
+    /// VENDOR_SPECIFIC_OUT transfer and returns the data to send back on BULK IN.
+    ///
+    pub fn on_vendor(&self, handler: impl FnMut(&[u8]) -> Vec<u8> + Send + 'static) {
+        self.state().vendor_handler = Some(Box::new(handler));
+    }
+
+    /// ### Push Response
+    ///
+    /// Queue a response message on BULK IN, as if the device produced output on its own.
+    ///
+    pub fn push_response(&self, data: impl Into<Vec<u8>>) {
+        self.state().responses.push_back(data.into());
+    }
+
+    /// ### Triggers
+    ///
+    /// Return the number of TRIGGER messages received.
+    ///
+    pub fn triggers(&self) -> usize {
+        self.state().triggers
+    }
+
+    /// ### Transfers
+    ///
+    /// Return every transfer written to the BULK OUT endpoint, in order.
+    ///
+    pub fn transfers(&self) -> Vec<Vec<u8>> {
+        self.state().transfers.clone()
+    }
+
+    /// ### Messages
+    ///
+    /// Return every complete device dependent message received, in order.
+    ///
+    pub fn messages(&self) -> Vec<Vec<u8>> {
+        self.state().messages.clone()
+    }
+
+    /// ### Vendor Messages
+    ///
+    /// Return the data of every VENDOR_SPECIFIC_OUT transfer received, in order.
+    ///
+    pub fn vendor_messages(&self) -> Vec<Vec<u8>> {
+        self.state().vendor_messages.clone()
+    }
+
+    /// ### Control Requests
+    ///
+    /// Return the bRequest of every control request received, in order.
+    ///
+    pub fn control_requests(&self) -> Vec<u8> {
+        self.state().control_requests.clone()
+    }
+
+    /// ### Cleared Halts
+    ///
+    /// Return the address of every endpoint whose halt was cleared, in order.
+    ///
+    pub fn cleared_halts(&self) -> Vec<u8> {
+        self.state().cleared_halts.clone()
+    }
+}
+
+impl SimulatorState {
+    /// The status byte with the MAV bit set when a response is waiting
+    fn status_byte(&self) -> u8 {
+        match self.responses.is_empty() {
+            true => self.status_byte,
+            false => self.status_byte | 0b0001_0000,
+        }
+    }
+
+    /// Handle a packet received on the BULK OUT endpoint
+    fn receive_packet(&mut self, packet: &[u8]) -> rusb::Result<()> {
+        let mut packet = packet;
+
+        // start a new transfer if none is in progress
+        if self.out_transfer.is_none() {
+            if packet.len() < misc::USBTMC_HEADER_SIZE {
+                return Err(rusb::Error::Pipe);
+            }
+            let (header, rest) = packet.split_at(misc::USBTMC_HEADER_SIZE);
+            packet = rest;
+
+            let btag = header[1];
+            if btag != !header[2] || btag == 0 {
+                return Err(rusb::Error::Pipe);
+            }
+            let transfer_size =
+                u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+
+            match header[0] {
+                bulk_msg_id::DEVICE_DEPENDENT_MSG_OUT => {
+                    self.last_out_btag = btag;
+                    self.out_transfer = Some(OutTransfer {
+                        btag,
+                        remaining: transfer_size,
+                        end_of_message: header[8] & 0b0000_0001 != 0,
+                        vendor_specific: false,
+                    });
+                }
+                bulk_msg_id::VENDOR_SPECIFIC_MSG_OUT => {
+                    self.last_out_btag = btag;
+                    self.out_transfer = Some(OutTransfer {
+                        btag,
+                        remaining: transfer_size,
+                        end_of_message: true,
+                        vendor_specific: true,
+                    });
+                }
+                bulk_msg_id::REQUEST_VENDOR_SPECIFIC_MSG_IN => {
+                    self.last_in_btag = btag;
+                    self.serve_vendor_request(btag, transfer_size);
+                    return Ok(());
+                }
+                bulk_msg_id::REQUEST_DEVICE_DEPENDENT_MSG_IN => {
+                    self.last_in_btag = btag;
+                    self.in_request = Some(InRequest {
+                        btag,
+                        transfer_size,
+                        term_char: match header[8] & 0b0000_0010 != 0 {
+                            true => Some(header[9]),
+                            false => None,
+                        },
+                    });
+                    self.serve_request();
+                    return Ok(());
+                }
+                bulk_msg_id::TRIGGER => {
+                    if self.usb488_interface_capabilities & 0b0000_0001 == 0 {
+                        return Err(rusb::Error::Pipe);
+                    }
+                    self.triggers += 1;
+                    return Ok(());
+                }
+                _ => return Err(rusb::Error::Pipe),
+            }
+        }
+
+        // collect the payload, the bytes after it in the packet are alignment bytes
+        let transfer = self.out_transfer.as_mut().unwrap();
+        let n = transfer.remaining.min(packet.len());
+        match transfer.vendor_specific {
+            true => self.vendor_transfer.extend_from_slice(&packet[..n]),
+            false => self.message.extend_from_slice(&packet[..n]),
+        }
+        self.bytes_received += n;
+        transfer.remaining -= n;
+
+        if transfer.remaining == 0 {
+            let end_of_message = transfer.end_of_message;
+            let vendor_specific = transfer.vendor_specific;
+            self.out_transfer = None;
+            self.bytes_received = 0;
+            if vendor_specific {
+                let data = std::mem::take(&mut self.vendor_transfer);
+                if let Some(handler) = self.vendor_handler.as_mut() {
+                    let response = handler(&data);
+                    self.vendor_responses.extend(response);
+                }
+                self.vendor_messages.push(data);
+            } else if end_of_message {
+                let message = std::mem::take(&mut self.message);
+                self.dispatch(message);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Answer a complete message
+    fn dispatch(&mut self, message: Vec<u8>) {
+        let text = String::from_utf8_lossy(&message).to_string();
+        if let Some((_, handler)) = self
+            .handlers
+            .iter_mut()
+            .find(|(query, _)| query.eq_ignore_ascii_case(text.trim()))
+        {
+            let response = handler(&text);
+            self.responses.push_back(response);
+        }
+        self.messages.push(message);
+        self.serve_request();
+    }
+
+    /// Build the BULK IN transfer answering the pending request, if there is data to send
+    fn serve_request(&mut self) {
+        let request = match &self.in_request {
+            Some(r) => r,
+            None => return,
+        };
+        let response = match self.responses.front_mut() {
+            Some(r) => r,
+            None => return,
+        };
+
+        // take the data, stopping at the termination character
+        let term_char = request
+            .term_char
+            .filter(|_| self.device_capabilities & 0b0000_0001 != 0);
+        let mut size = request.transfer_size.min(response.len());
+        let mut term_char_found = false;
+        if let Some(tc) = term_char {
+            if let Some(pos) = response[..size].iter().position(|v| *v == tc) {
+                size = pos + 1;
+                term_char_found = true;
+            }
+        }
+        let data: Vec<u8> = response.drain(..size).collect();
+        let end_of_message = response.is_empty();
+        if end_of_message {
+            self.responses.pop_front();
+        }
+
+        // build the transfer
+        let mut transfer = vec![0x00; misc::USBTMC_HEADER_SIZE];
+        transfer[0] = bulk_msg_id::DEVICE_DEPENDENT_MSG_IN;
+        transfer[1] = request.btag;
+        transfer[2] = !request.btag;
+        transfer[4..8].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        if end_of_message {
+            transfer[8] |= 0b0000_0001;
+        }
+        if term_char_found {
+            transfer[8] |= 0b0000_0010;
+        }
+        transfer.extend_from_slice(&data);
+        while !transfer.len().is_multiple_of(4) {
+            transfer.push(0x00);
+        }
+
+        self.bytes_transfered = data.len();
+        self.in_transfers.push_back(transfer);
+        self.in_request = None;
+    }
+
+    /// Build the BULK IN transfer answering a vendor specific request
+    fn serve_vendor_request(&mut self, btag: u8, transfer_size: usize) {
+        let size = transfer_size.min(self.vendor_responses.len());
+        let data: Vec<u8> = self.vendor_responses.drain(..size).collect();
+
+        let mut transfer = vec![0x00; misc::USBTMC_HEADER_SIZE];
+        transfer[0] = bulk_msg_id::VENDOR_SPECIFIC_MSG_IN;
+        transfer[1] = btag;
+        transfer[2] = !btag;
+        transfer[4..8].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        transfer.extend_from_slice(&data);
+        while !transfer.len().is_multiple_of(4) {
+            transfer.push(0x00);
+        }
+
+        self.bytes_transfered = data.len();
+        self.in_transfers.push_back(transfer);
+    }
+
+    /// Answer a control request, returning the response
+    fn control(&mut self, request: u8, value: u16) -> rusb::Result<Vec<u8>> {
+        self.control_requests.push(request);
+
+        let response = match request {
+            control_requests::GET_CAPABILITIES => {
+                let mut response = vec![0x00; 0x18];
+                response[0] = usbtmc_status::STATUS_SUCCESS;
+                response[2..4].copy_from_slice(&0x0100u16.to_le_bytes());
+                response[4] = self.interface_capabilities;
+                response[5] = self.device_capabilities;
+                response[12..14].copy_from_slice(&0x0100u16.to_le_bytes());
+                response[14] = self.usb488_interface_capabilities;
+                response[15] = self.usb488_device_capabilities;
+                response
+            }
+            control_requests::INITIATE_CLEAR => {
+                // the data already in the Bulk-IN FIFO must be read by the host
+                self.out_transfer = None;
+                self.message.clear();
+                self.responses.clear();
+                self.vendor_transfer.clear();
+                self.vendor_responses.clear();
+                self.in_request = None;
+                vec![usbtmc_status::STATUS_SUCCESS]
+            }
+            control_requests::CHECK_CLEAR_STATUS => match self.in_transfers.is_empty() {
+                true => vec![usbtmc_status::STATUS_SUCCESS, 0x00],
+                false => vec![usbtmc_status::STATUS_PENDING, 0b0000_0001],
+            },
+            control_requests::INITIATE_ABORT_BULK_OUT => {
+                let btag = value as u8;
+                let status = match &self.out_transfer {
+                    Some(t) if t.btag == btag => {
+                        self.out_transfer = None;
+                        self.message.clear();
+                        usbtmc_status::STATUS_SUCCESS
+                    }
+                    Some(_) => usbtmc_status::STATUS_TRANSFER_NOT_IN_PROGRESS,
+                    None => usbtmc_status::STATUS_FAILED,
+                };
+                vec![status, self.last_out_btag]
+            }
+            control_requests::CHECK_ABORT_BULK_OUT_STATUS => {
+                let mut response = vec![usbtmc_status::STATUS_SUCCESS, 0x00, 0x00, 0x00];
+                response.extend_from_slice(&(self.bytes_received as u32).to_le_bytes());
+                response
+            }
+            control_requests::INITIATE_ABORT_BULK_IN => {
+                let btag = value as u8;
+                let in_progress = self.in_request.is_some() || !self.in_transfers.is_empty();
+                let status = match in_progress {
+                    true if self.last_in_btag == btag => {
+                        self.in_request = None;
+                        self.in_transfers.clear();
+                        self.responses.pop_front();
+                        usbtmc_status::STATUS_SUCCESS
+                    }
+                    true => usbtmc_status::STATUS_TRANSFER_NOT_IN_PROGRESS,
+                    false => usbtmc_status::STATUS_FAILED,
+                };
+                vec![status, self.last_in_btag]
+            }
+            control_requests::CHECK_ABORT_BULK_IN_STATUS => {
+                let mut response = vec![usbtmc_status::STATUS_SUCCESS, 0x00, 0x00, 0x00];
+                response.extend_from_slice(&(self.bytes_transfered as u32).to_le_bytes());
+                response
+            }
+            control_requests::INDICATOR_PULSE => {
+                match self.interface_capabilities & 0b0000_0100 != 0 {
+                    true => vec![usbtmc_status::STATUS_SUCCESS],
+                    false => return Err(rusb::Error::Pipe),
+                }
+            }
+            control_requests::REN_CONTROL
+            | control_requests::GO_TO_LOCAL
+            | control_requests::LOCAL_LOCKOUT => {
+                match self.usb488_interface_capabilities & 0b0000_0010 != 0 {
+                    true => vec![usbtmc_status::STATUS_SUCCESS],
+                    false => return Err(rusb::Error::Pipe),
+                }
+            }
+            control_requests::READ_STATUS_BYTE => {
+                let btag = (value & 0x7F) as u8;
+                let status_byte = self.status_byte();
+                match self.has_interrupt_endpoint {
+                    // the FIFO holds a single notification
+                    true if !self.interrupt_in.is_empty() => {
+                        vec![usbtmc_status::STATUS_INTERRUPT_IN_BUSY, btag, 0x00]
+                    }
+                    true => {
+                        self.interrupt_in
+                            .push_back(vec![0b1000_0000 | btag, status_byte]);
+                        vec![usbtmc_status::STATUS_SUCCESS, btag, 0x00]
+                    }
+                    false => vec![usbtmc_status::STATUS_SUCCESS, btag, status_byte],
+                }
+            }
+            _ => return Err(rusb::Error::Pipe),
+        };
+
+        Ok(response)
+    }
+}
+
+/// Copy data into a transfer buffer, keeping what does not fit
+fn copy_into(data: &mut Vec<u8>, buf: &mut [u8]) -> usize {
+    let n = data.len().min(buf.len());
+    buf[..n].copy_from_slice(&data[..n]);
+    data.drain(..n);
+    n
+}
+
+impl Transport for SimulatedDevice {
+    fn write_bulk(&self, endpoint: u8, buf: &[u8], _timeout: Duration) -> rusb::Result<usize> {
+        if endpoint != BULK_OUT_ADDRESS {
+            return Err(rusb::Error::InvalidParam);
+        }
+        let mut state = self.state();
+        state.transfers.push(buf.to_vec());
+        state.receive_packet(buf)?;
+        Ok(buf.len())
+    }
+
+    fn read_bulk(&self, endpoint: u8, buf: &mut [u8], _timeout: Duration) -> rusb::Result<usize> {
+        if endpoint != BULK_IN_ADDRESS {
+            return Err(rusb::Error::InvalidParam);
+        }
+        let mut state = self.state();
+        let transfer = match state.in_transfers.front_mut() {
+            Some(t) => t,
+            None => return Err(rusb::Error::Timeout),
+        };
+        let n = copy_into(transfer, buf);
+        if transfer.is_empty() {
+            state.in_transfers.pop_front();
+        }
+        Ok(n)
+    }
+
+    fn read_control(
+        &self,
+        _request_type: u8,
+        request: u8,
+        value: u16,
+        _index: u16,
+        buf: &mut [u8],
+        _timeout: Duration,
+    ) -> rusb::Result<usize> {
+        let response = self.state().control(request, value);
+        self.notify_interrupt();
+        Ok(copy_into(&mut response?, buf))
+    }
+
+    fn write_control(
+        &self,
+        _request_type: u8,
+        request: u8,
+        _value: u16,
+        _index: u16,
+        _buf: &[u8],
+        _timeout: Duration,
+    ) -> rusb::Result<usize> {
+        // USBTMC does not define control requests sending data to the device
+        self.state().control_requests.push(request);
+        Err(rusb::Error::Pipe)
+    }
+
+    fn read_interrupt(
+        &self,
+        endpoint: u8,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> rusb::Result<usize> {
+        if endpoint != INTERRUPT_IN_ADDRESS {
+            return Err(rusb::Error::InvalidParam);
+        }
+        // wait for a packet like a device would
+        let (mut state, _) = self
+            .0
+             .1
+            .wait_timeout_while(self.state(), timeout, |state| state.interrupt_in.is_empty())
+            .unwrap();
+        match state.interrupt_in.pop_front() {
+            Some(mut packet) => Ok(copy_into(&mut packet, buf)),
+            None => Err(rusb::Error::Timeout),
+        }
+    }
+
+    fn clear_halt(&self, endpoint: u8) -> rusb::Result<()> {
+        self.state().cleared_halts.push(endpoint);
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+//! ## Status
+//!
+//! The IEEE 488.2 status byte and the standard status registers of instruments.
+//!
+//! Each register is a set of flags. Flags are combined with `|` and tested with
+//! `contains`, for example `StatusByte::MAV | StatusByte::ESB`.
+//!
+
+use std::ops::{BitAnd, BitOr};
+
+/// Implement the flag operations shared by the registers
+macro_rules! register {
+    ($name:ident, $bits:ty) => {
+        impl $name {
+            /// ### Empty
+            ///
+            /// Return a register with no flag set.
+            ///
+            pub const fn empty() -> $name {
+                $name(0)
+            }
+
+            /// ### Bits
+            ///
+            /// Return the raw value of the register.
+            ///
+            pub const fn bits(&self) -> $bits {
+                self.0
+            }
+
+            /// ### Contains
+            ///
+            /// Whether all the flags of `other` are set.
+            ///
+            pub const fn contains(&self, other: $name) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            /// ### Is Empty
+            ///
+            /// Whether no flag is set.
+            ///
+            pub const fn is_empty(&self) -> bool {
+                self.0 == 0
+            }
+        }
+
+        impl From<$bits> for $name {
+            fn from(bits: $bits) -> $name {
+                $name(bits)
+            }
+        }
+
+        impl From<$name> for $bits {
+            fn from(register: $name) -> $bits {
+                register.0
+            }
+        }
+
+        impl BitOr for $name {
+            type Output = $name;
+
+            fn bitor(self, other: $name) -> $name {
+                $name(self.0 | other.0)
+            }
+        }
+
+        impl BitAnd for $name {
+            type Output = $name;
+
+            fn bitand(self, other: $name) -> $name {
+                $name(self.0 & other.0)
+            }
+        }
+    };
+}
+
+/// ### Status Byte
+///
+/// The IEEE 488 status byte of a device, as read with READ_STATUS_BYTE or `*STB?`, and the
+/// service request enable mask set with `*SRE`.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct StatusByte(u8);
+
+register!(StatusByte, u8);
+
+impl StatusByte {
+    /// Error/event queue not empty (bit 2, SCPI)
+    pub const EAV: StatusByte = StatusByte(0b0000_0100);
+    /// Questionable status summary (bit 3, SCPI)
+    pub const QUES: StatusByte = StatusByte(0b0000_1000);
+    /// Message available in the output queue (bit 4)
+    pub const MAV: StatusByte = StatusByte(0b0001_0000);
+    /// Standard event status summary (bit 5)
+    pub const ESB: StatusByte = StatusByte(0b0010_0000);
+    /// Request service, or master summary status when read with `*STB?` (bit 6)
+    pub const RQS: StatusByte = StatusByte(0b0100_0000);
+    /// Operation status summary (bit 7, SCPI)
+    pub const OPER: StatusByte = StatusByte(0b1000_0000);
+
+    /// ### Error Available
+    ///
+    /// The EAV bit (bit 2), set when the error/event queue is not empty.
+    ///
+    pub fn error_available(&self) -> bool {
+        self.contains(StatusByte::EAV)
+    }
+
+    /// ### Questionable Summary
+    ///
+    /// The QUES bit (bit 3), summary of the questionable status register.
+    ///
+    pub fn questionable_summary(&self) -> bool {
+        self.contains(StatusByte::QUES)
+    }
+
+    /// ### Message Available
+    ///
+    /// The MAV bit (bit 4), set when a response is waiting in the output queue.
+    ///
+    pub fn message_available(&self) -> bool {
+        self.contains(StatusByte::MAV)
+    }
+
+    /// ### Event Status
+    ///
+    /// The ESB bit (bit 5), summary of the standard event status register.
+    ///
+    pub fn event_status(&self) -> bool {
+        self.contains(StatusByte::ESB)
+    }
+
+    /// ### Request Service
+    ///
+    /// The RQS bit (bit 6), set when the device requests service.
+    ///
+    pub fn request_service(&self) -> bool {
+        self.contains(StatusByte::RQS)
+    }
+
+    /// ### Master Summary
+    ///
+    /// The MSS bit, which `*STB?` reports in place of RQS (bit 6).
+    ///
+    pub fn master_summary(&self) -> bool {
+        self.contains(StatusByte::RQS)
+    }
+
+    /// ### Operation Summary
+    ///
+    /// The OPER bit (bit 7), summary of the operation status register.
+    ///
+    pub fn operation_summary(&self) -> bool {
+        self.contains(StatusByte::OPER)
+    }
+}
+
+/// ### Event Status
+///
+/// The IEEE 488.2 standard event status register, as read with `*ESR?`, and the event
+/// status enable mask set with `*ESE`.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct EventStatus(u8);
+
+register!(EventStatus, u8);
+
+impl EventStatus {
+    /// Operation complete (bit 0)
+    pub const OPC: EventStatus = EventStatus(0b0000_0001);
+    /// Request control (bit 1)
+    pub const RQC: EventStatus = EventStatus(0b0000_0010);
+    /// Query error (bit 2)
+    pub const QYE: EventStatus = EventStatus(0b0000_0100);
+    /// Device dependent error (bit 3)
+    pub const DDE: EventStatus = EventStatus(0b0000_1000);
+    /// Execution error (bit 4)
+    pub const EXE: EventStatus = EventStatus(0b0001_0000);
+    /// Command error (bit 5)
+    pub const CME: EventStatus = EventStatus(0b0010_0000);
+    /// User request (bit 6)
+    pub const URQ: EventStatus = EventStatus(0b0100_0000);
+    /// Power on (bit 7)
+    pub const PON: EventStatus = EventStatus(0b1000_0000);
+
+    /// ### Operation Complete
+    ///
+    /// The OPC bit (bit 0), set by `*OPC` once the pending operations are complete.
+    ///
+    pub fn operation_complete(&self) -> bool {
+        self.contains(EventStatus::OPC)
+    }
+
+    /// ### Has Error
+    ///
+    /// Whether any of the query, device dependent, execution or command error bits is set.
+    ///
+    pub fn has_error(&self) -> bool {
+        !(*self & (EventStatus::QYE | EventStatus::DDE | EventStatus::EXE | EventStatus::CME))
+            .is_empty()
+    }
+}
+
+/// ### Operation Status
+///
+/// The SCPI operation status register (`STATus:OPERation`).
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct OperationStatus(u16);
+
+register!(OperationStatus, u16);
+
+impl OperationStatus {
+    /// Calibrating (bit 0)
+    pub const CALIBRATING: OperationStatus = OperationStatus(1 << 0);
+    /// Settling (bit 1)
+    pub const SETTLING: OperationStatus = OperationStatus(1 << 1);
+    /// Changing range (bit 2)
+    pub const RANGING: OperationStatus = OperationStatus(1 << 2);
+    /// Sweeping (bit 3)
+    pub const SWEEPING: OperationStatus = OperationStatus(1 << 3);
+    /// Measuring (bit 4)
+    pub const MEASURING: OperationStatus = OperationStatus(1 << 4);
+    /// Waiting for trigger (bit 5)
+    pub const WAITING_FOR_TRIGGER: OperationStatus = OperationStatus(1 << 5);
+    /// Waiting for arm (bit 6)
+    pub const WAITING_FOR_ARM: OperationStatus = OperationStatus(1 << 6);
+    /// Correcting (bit 7)
+    pub const CORRECTING: OperationStatus = OperationStatus(1 << 7);
+    /// Instrument summary (bit 13)
+    pub const INSTRUMENT_SUMMARY: OperationStatus = OperationStatus(1 << 13);
+    /// Program running (bit 14)
+    pub const PROGRAM_RUNNING: OperationStatus = OperationStatus(1 << 14);
+}
+
+/// ### Questionable Status
+///
+/// The SCPI questionable status register (`STATus:QUEStionable`).
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct QuestionableStatus(u16);
+
+register!(QuestionableStatus, u16);
+
+impl QuestionableStatus {
+    /// Questionable voltage (bit 0)
+    pub const VOLTAGE: QuestionableStatus = QuestionableStatus(1 << 0);
+    /// Questionable current (bit 1)
+    pub const CURRENT: QuestionableStatus = QuestionableStatus(1 << 1);
+    /// Questionable time (bit 2)
+    pub const TIME: QuestionableStatus = QuestionableStatus(1 << 2);
+    /// Questionable power (bit 3)
+    pub const POWER: QuestionableStatus = QuestionableStatus(1 << 3);
+    /// Questionable temperature (bit 4)
+    pub const TEMPERATURE: QuestionableStatus = QuestionableStatus(1 << 4);
+    /// Questionable frequency (bit 5)
+    pub const FREQUENCY: QuestionableStatus = QuestionableStatus(1 << 5);
+    /// Questionable phase (bit 6)
+    pub const PHASE: QuestionableStatus = QuestionableStatus(1 << 6);
+    /// Questionable modulation (bit 7)
+    pub const MODULATION: QuestionableStatus = QuestionableStatus(1 << 7);
+    /// Questionable calibration (bit 8)
+    pub const CALIBRATION: QuestionableStatus = QuestionableStatus(1 << 8);
+    /// Instrument summary (bit 13)
+    pub const INSTRUMENT_SUMMARY: QuestionableStatus = QuestionableStatus(1 << 13);
+    /// Command warning (bit 14)
+    pub const COMMAND_WARNING: QuestionableStatus = QuestionableStatus(1 << 14);
+}
@@ -0,0 +1,95 @@
+//! ## Stream
+//!
+//! Adapter exposing a USBTMC session as a byte stream, to use instruments with code built
+//! around [`std::io::Read`] and [`std::io::Write`].
+//!
+
+use std::io;
+
+use crate::error::Error;
+use crate::types::ReadEnd;
+use crate::UsbtmcClient;
+
+/// ### USBTMC Stream
+///
+/// A byte stream over the bulk endpoints of a device, obtained with
+/// [`UsbtmcClient::stream`].
+///
+/// Written data is buffered until the stream is flushed, then sent as a single message.
+/// Reads request data from the device as needed and return `0` at the end of the message.
+/// Pending writes are flushed before reading, and when the stream is dropped.
+///
+#[derive(Debug)]
+pub struct UsbtmcStream<'a> {
+    client: &'a UsbtmcClient,
+    write_buffer: Vec<u8>,
+    end_of_message: bool,
+}
+
+impl<'a> UsbtmcStream<'a> {
+    pub(crate) fn new(client: &'a UsbtmcClient) -> UsbtmcStream<'a> {
+        UsbtmcStream {
+            client,
+            write_buffer: Vec::new(),
+            end_of_message: false,
+        }
+    }
+}
+
+impl io::Write for UsbtmcStream<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.write_buffer.is_empty() {
+            return Ok(());
+        }
+
+        self.client
+            .write_raw(&self.write_buffer)
+            .map_err(io_error)?;
+        self.write_buffer.clear();
+
+        // the response to the new message is yet to be read
+        self.end_of_message = false;
+
+        Ok(())
+    }
+}
+
+impl io::Read for UsbtmcStream<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::Write::flush(self)?;
+
+        if buf.is_empty() || self.end_of_message {
+            return Ok(0);
+        }
+
+        // skip empty transfers until data or the end of the message
+        loop {
+            let (len, end) = self.client.read_into(buf).map_err(io_error)?;
+            if end == ReadEnd::EndOfMessage {
+                self.end_of_message = true;
+            }
+            if len > 0 || self.end_of_message {
+                return Ok(len);
+            }
+        }
+    }
+}
+
+impl Drop for UsbtmcStream<'_> {
+    fn drop(&mut self) {
+        let _ = io::Write::flush(self);
+    }
+}
+
+/// Convert an error of the client to an I/O error, keeping timeouts recognizable
+fn io_error(error: Error) -> io::Error {
+    match error.is_timeout() {
+        true => io::Error::new(io::ErrorKind::TimedOut, error),
+        false => io::Error::other(error),
+    }
+}
@@ -0,0 +1,354 @@
+//! ## Transport
+//!
+//! The link between the USBTMC protocol and the USB device.
+//!
+//! Every request the client sends goes through a [`Transport`]. The default
+//! `UsbTransport` talks to a real device through libusb, while the
+//! [`MockTransport`] is an in-memory scripted device to run the driver without hardware.
+//!
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+use rusb::{Context, DeviceHandle};
+
+use crate::types::DeviceMode;
+
+/// ### Transport
+///
+/// The USB operations required to drive a USBTMC device.
+///
+/// The methods mirror the ones of a libusb device handle, and return the number of bytes
+/// transfered where applicable. They can be called from several threads at once, for example
+/// to wait on the INTERRUPT IN endpoint while using the bulk endpoints.
+///
+pub trait Transport: std::fmt::Debug + Send + Sync {
+    /// Write data to a BULK OUT endpoint
+    fn write_bulk(&self, endpoint: u8, buf: &[u8], timeout: Duration) -> rusb::Result<usize>;
+
+    /// Read data from a BULK IN endpoint
+    fn read_bulk(&self, endpoint: u8, buf: &mut [u8], timeout: Duration) -> rusb::Result<usize>;
+
+    /// Send a control request which reads data from the device
+    fn read_control(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> rusb::Result<usize>;
+
+    /// Send a control request which writes data to the device
+    fn write_control(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        buf: &[u8],
+        timeout: Duration,
+    ) -> rusb::Result<usize>;
+
+    /// Read data from an INTERRUPT IN endpoint
+    fn read_interrupt(
+        &self,
+        endpoint: u8,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> rusb::Result<usize>;
+
+    /// Clear the halt/stall condition of an endpoint
+    fn clear_halt(&self, endpoint: u8) -> rusb::Result<()>;
+}
+
+/// ### USB Transport
+///
+/// Transport to a real device through a libusb device handle.
+///
+/// The interface is claimed for as long as the transport lives. When dropped, the interface
+/// is released and the kernel driver is reattached if it was detached on connection.
+///
+#[derive(Debug)]
+pub(crate) struct UsbTransport {
+    handle: DeviceHandle<Context>,
+    mode: DeviceMode,
+}
+
+impl UsbTransport {
+    /// ### New
+    ///
+    /// Wrap a device handle whose interface was claimed with the given mode.
+    ///
+    pub(crate) fn new(handle: DeviceHandle<Context>, mode: DeviceMode) -> UsbTransport {
+        UsbTransport { handle, mode }
+    }
+}
+
+impl Transport for UsbTransport {
+    fn write_bulk(&self, endpoint: u8, buf: &[u8], timeout: Duration) -> rusb::Result<usize> {
+        self.handle.write_bulk(endpoint, buf, timeout)
+    }
+
+    fn read_bulk(&self, endpoint: u8, buf: &mut [u8], timeout: Duration) -> rusb::Result<usize> {
+        self.handle.read_bulk(endpoint, buf, timeout)
+    }
+
+    fn read_control(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> rusb::Result<usize> {
+        self.handle
+            .read_control(request_type, request, value, index, buf, timeout)
+    }
+
+    fn write_control(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        buf: &[u8],
+        timeout: Duration,
+    ) -> rusb::Result<usize> {
+        self.handle
+            .write_control(request_type, request, value, index, buf, timeout)
+    }
+
+    fn read_interrupt(
+        &self,
+        endpoint: u8,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> rusb::Result<usize> {
+        self.handle.read_interrupt(endpoint, buf, timeout)
+    }
+
+    fn clear_halt(&self, endpoint: u8) -> rusb::Result<()> {
+        self.handle.clear_halt(endpoint)
+    }
+}
+
+impl Drop for UsbTransport {
+    fn drop(&mut self) {
+        // RESET THE CONFIGURATION
+        // Release the interface
+        self.handle
+            .release_interface(self.mode.interface_number)
+            .expect("failed to release device usb interface");
+        // Reattach the kernel driver if it was disconnected
+        if self.mode.has_kernel_driver {
+            self.handle
+                .attach_kernel_driver(self.mode.interface_number)
+                .expect("failed to attach kernel driver to usb device");
+        };
+    }
+}
+
+/// ### Control Request
+///
+/// A control request received by the [`MockTransport`].
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlRequest {
+    /// The bmRequestType field
+    pub request_type: u8,
+    /// The bRequest field
+    pub request: u8,
+    /// The wValue field
+    pub value: u16,
+    /// The wIndex field
+    pub index: u16,
+    /// The data sent with the request (empty for requests reading from the device)
+    pub data: Vec<u8>,
+}
+
+/// ### Mock Transport
+///
+/// An in-memory scripted device.
+///
+/// Responses are queued ahead of time and handed out in order on each IN transfer. Reading
+/// from an empty queue times out, like a device with nothing to send. Every OUT transfer and
+/// control request is recorded so it can be inspected afterwards.
+///
+/// The mock is cheap to clone and all clones share the same script, so one copy can be given
+/// to the client while the test keeps the other.
+///
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport(Arc<(Mutex<MockState>, Condvar)>);
+
+#[derive(Debug, Default)]
+struct MockState {
+    bulk_in: VecDeque<rusb::Result<Vec<u8>>>,
+    control_in: VecDeque<rusb::Result<Vec<u8>>>,
+    interrupt_in: VecDeque<rusb::Result<Vec<u8>>>,
+    bulk_out: Vec<Vec<u8>>,
+    control_requests: Vec<ControlRequest>,
+    cleared_halts: Vec<u8>,
+}
+
+impl MockTransport {
+    /// ### New
+    ///
+    /// Return a mock with an empty script.
+    ///
+    pub fn new() -> MockTransport {
+        MockTransport::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.0 .0.lock().unwrap()
+    }
+
+    /// ### Push Bulk In
+    ///
+    /// Queue a response for the next BULK IN read.
+    ///
+    pub fn push_bulk_in(&self, data: impl Into<Vec<u8>>) {
+        self.state().bulk_in.push_back(Ok(data.into()));
+    }
+
+    /// ### Push Control In
+    ///
+    /// Queue a response for the next control request reading from the device.
+    ///
+    pub fn push_control_in(&self, data: impl Into<Vec<u8>>) {
+        self.state().control_in.push_back(Ok(data.into()));
+    }
+
+    /// ### Push Interrupt In
+    ///
+    /// Queue a packet for the next INTERRUPT IN read.
+    ///
+    pub fn push_interrupt_in(&self, data: impl Into<Vec<u8>>) {
+        self.state().interrupt_in.push_back(Ok(data.into()));
+        self.0 .1.notify_all();
+    }
+
+    /// ### Push Bulk In Error
+    ///
+    /// Make the next BULK IN read fail with `error`.
+    ///
+    pub fn push_bulk_in_error(&self, error: rusb::Error) {
+        self.state().bulk_in.push_back(Err(error));
+    }
+
+    /// ### Push Control In Error
+    ///
+    /// Make the next control request reading from the device fail with `error`.
+    ///
+    pub fn push_control_in_error(&self, error: rusb::Error) {
+        self.state().control_in.push_back(Err(error));
+    }
+
+    /// ### Bulk Out
+    ///
+    /// Return every transfer written to the BULK OUT endpoint, in order.
+    ///
+    pub fn bulk_out(&self) -> Vec<Vec<u8>> {
+        self.state().bulk_out.clone()
+    }
+
+    /// ### Control Requests
+    ///
+    /// Return every control request sent to the device, in order.
+    ///
+    pub fn control_requests(&self) -> Vec<ControlRequest> {
+        self.state().control_requests.clone()
+    }
+
+    /// ### Cleared Halts
+    ///
+    /// Return the address of every endpoint whose halt was cleared, in order.
+    ///
+    pub fn cleared_halts(&self) -> Vec<u8> {
+        self.state().cleared_halts.clone()
+    }
+}
+
+/// Copy a queued response into a transfer buffer
+fn pop_into(queue: &mut VecDeque<rusb::Result<Vec<u8>>>, buf: &mut [u8]) -> rusb::Result<usize> {
+    let data = queue.pop_front().unwrap_or(Err(rusb::Error::Timeout))?;
+    if data.len() > buf.len() {
+        return Err(rusb::Error::Overflow);
+    }
+    buf[..data.len()].copy_from_slice(&data);
+    Ok(data.len())
+}
+
+impl Transport for MockTransport {
+    fn write_bulk(&self, _endpoint: u8, buf: &[u8], _timeout: Duration) -> rusb::Result<usize> {
+        self.state().bulk_out.push(buf.to_vec());
+        Ok(buf.len())
+    }
+
+    fn read_bulk(&self, _endpoint: u8, buf: &mut [u8], _timeout: Duration) -> rusb::Result<usize> {
+        pop_into(&mut self.state().bulk_in, buf)
+    }
+
+    fn read_control(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        buf: &mut [u8],
+        _timeout: Duration,
+    ) -> rusb::Result<usize> {
+        let mut state = self.state();
+        state.control_requests.push(ControlRequest {
+            request_type,
+            request,
+            value,
+            index,
+            data: Vec::new(),
+        });
+        pop_into(&mut state.control_in, buf)
+    }
+
+    fn write_control(
+        &self,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        buf: &[u8],
+        _timeout: Duration,
+    ) -> rusb::Result<usize> {
+        self.state().control_requests.push(ControlRequest {
+            request_type,
+            request,
+            value,
+            index,
+            data: buf.to_vec(),
+        });
+        Ok(buf.len())
+    }
+
+    fn read_interrupt(
+        &self,
+        _endpoint: u8,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> rusb::Result<usize> {
+        // wait for a packet like a device would
+        let (mut state, _) = self
+            .0
+             .1
+            .wait_timeout_while(self.state(), timeout, |state| state.interrupt_in.is_empty())
+            .unwrap();
+        pop_into(&mut state.interrupt_in, buf)
+    }
+
+    fn clear_halt(&self, endpoint: u8) -> rusb::Result<()> {
+        self.state().cleared_halts.push(endpoint);
+        Ok(())
+    }
+}
@@ -3,25 +3,32 @@
 //! The different types used across the crate
 //!
 
+use std::fmt;
 use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::Duration;
 
-use rusb::{Context, DeviceHandle, Direction, TransferType};
+use rusb::{Direction, TransferType};
+
+use crate::resource::VisaResource;
+use crate::transport::Transport;
 
 /// ### Handle
 ///
-/// Alias for a libusb device handle wrapped in an Rc and RefCell.
+/// Alias for the device transport wrapped in an Arc.
+///
+/// The transport is shared, so the INTERRUPT IN endpoint can be listened to while other
+/// requests are made.
 ///
 #[derive(Debug, Clone)]
-pub struct Handle(Arc<Mutex<DeviceHandle<Context>>>);
+pub struct Handle(Arc<dyn Transport>);
 
 impl Handle {
-    pub fn new(handle: DeviceHandle<Context>) -> Handle {
-        Handle(Arc::new(Mutex::new(handle)))
+    pub fn new(transport: impl Transport + 'static) -> Handle {
+        Handle(Arc::new(transport))
     }
 
-    pub fn borrow(&self) -> MutexGuard<'_, DeviceHandle<Context>> {
-        self.0.lock().unwrap()
+    pub fn borrow(&self) -> &dyn Transport {
+        self.0.as_ref()
     }
 }
 
@@ -41,6 +48,75 @@ impl Timeout {
     }
 }
 
+/// ### Term Char
+///
+/// Alias for the termination character wrapped in an Arc and Mutex.
+/// `None` when reads don't stop on a termination character.
+///
+#[derive(Debug, Clone)]
+pub struct TermChar(Arc<Mutex<Option<u8>>>);
+
+impl TermChar {
+    pub fn new(term_char: Option<u8>) -> TermChar {
+        TermChar(Arc::new(Mutex::new(term_char)))
+    }
+
+    pub fn borrow(&self) -> MutexGuard<'_, Option<u8>> {
+        self.0.lock().unwrap()
+    }
+}
+
+/// ### Transfer Size
+///
+/// Alias for the largest TransferSize requested from the device wrapped in an Arc and Mutex.
+///
+#[derive(Debug, Clone)]
+pub struct TransferSize(Arc<Mutex<u32>>);
+
+impl TransferSize {
+    pub fn new(transfer_size: u32) -> TransferSize {
+        TransferSize(Arc::new(Mutex::new(transfer_size)))
+    }
+
+    pub fn borrow(&self) -> MutexGuard<'_, u32> {
+        self.0.lock().unwrap()
+    }
+}
+
+/// ### Message Size
+///
+/// Alias for the largest payload sent to the device in a single message wrapped in an Arc and Mutex.
+///
+#[derive(Debug, Clone)]
+pub struct MessageSize(Arc<Mutex<u32>>);
+
+impl MessageSize {
+    pub fn new(message_size: u32) -> MessageSize {
+        MessageSize(Arc::new(Mutex::new(message_size)))
+    }
+
+    pub fn borrow(&self) -> MutexGuard<'_, u32> {
+        self.0.lock().unwrap()
+    }
+}
+
+/// ### Checked
+///
+/// Alias for whether the error queue is checked after commands wrapped in an Arc and Mutex.
+///
+#[derive(Debug, Clone)]
+pub struct Checked(Arc<Mutex<bool>>);
+
+impl Checked {
+    pub fn new(checked: bool) -> Checked {
+        Checked(Arc::new(Mutex::new(checked)))
+    }
+
+    pub fn borrow(&self) -> MutexGuard<'_, bool> {
+        self.0.lock().unwrap()
+    }
+}
+
 /// ### bTag
 ///
 /// The bTag element used to identify a bulk request.
@@ -65,7 +141,7 @@ impl BTag {
     ///
     pub fn get(&self) -> u8 {
         let mut btag = self.0.lock().unwrap();
-        let output = (*btag).clone();
+        let output = *btag;
 
         if *btag == 255 {
             *btag = 1;
@@ -75,13 +151,27 @@ impl BTag {
 
         output
     }
+
+    /// ### Last
+    ///
+    /// Return the last bTag value given, without incrementing it
+    ///
+    pub fn last(&self) -> u8 {
+        let btag = self.0.lock().unwrap();
+
+        if *btag == 1 {
+            255
+        } else {
+            *btag - 1
+        }
+    }
 }
 
 /// ### Control BTag
-/// 
+///
 /// A 7-bit bTag specifically made for the reading the status byte through
 /// the control endpoint.
-/// 
+///
 #[derive(Debug, Clone)]
 pub struct CtlBTag(Arc<Mutex<u8>>);
 
@@ -100,7 +190,7 @@ impl CtlBTag {
     ///
     pub fn get(&self) -> u8 {
         let mut btag = self.0.lock().unwrap();
-        let output = (*btag).clone();
+        let output = *btag;
 
         if *btag == 127 {
             *btag = 2;
@@ -112,6 +202,31 @@ impl CtlBTag {
     }
 }
 
+/// ### Read End
+///
+/// The reason a read from the device ended.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadEnd {
+    /// The device sent the end of the message (EOM)
+    EndOfMessage,
+    /// The device sent the termination character
+    TermChar,
+    /// The requested number of bytes was read before the end of the message
+    TransferSize,
+}
+
+/// ### Response
+///
+/// Data read from the device, and the reason the read ended.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    /// The data read
+    pub data: Vec<u8>,
+    /// Why the read ended
+    pub end: ReadEnd,
+}
 
 /// USB device address
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -131,11 +246,53 @@ pub struct DeviceId {
     pub product_id: u16,
 }
 
+/// USB device serial number, as read from its string descriptor
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SerialNumber(pub String);
+
+impl From<&str> for SerialNumber {
+    fn from(serial_number: &str) -> SerialNumber {
+        SerialNumber(serial_number.to_string())
+    }
+}
+
 /// USB device info
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+///
+/// The strings are read from the string descriptors of the device, and are `None` if the
+/// device has none or they can't be read, for example without the permission to open it.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct DeviceInfo {
     pub id: DeviceId,
     pub address: DeviceAddr,
+    /// The manufacturer of the device
+    pub manufacturer: Option<String>,
+    /// The product name of the device
+    pub product: Option<String>,
+    /// The serial number of the device
+    pub serial_number: Option<String>,
+}
+
+impl DeviceInfo {
+    /// ### Resource
+    ///
+    /// Get the VISA resource string of the device.
+    ///
+    pub fn resource(&self) -> VisaResource {
+        VisaResource {
+            board: 0,
+            vendor_id: self.id.vendor_id,
+            product_id: self.id.product_id,
+            serial_number: self.serial_number.clone(),
+            interface_number: None,
+        }
+    }
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.resource().fmt(f)
+    }
 }
 
 /// ### Device Mode
@@ -190,6 +347,7 @@ pub struct UsbtmcEndpoints {
 ///
 #[derive(Clone, Debug)]
 pub struct Capabilities {
+    /// The version of the USBTMC spec the device complies to, in BCD
     pub bcd_version: u16,
     /// Can accept a control command for pulse
     pub accepts_indicator_pulse_request: bool,
@@ -199,4 +357,30 @@ pub struct Capabilities {
     pub is_listen_only: bool,
     /// When returning data, it has a terminator character in the data
     pub supports_bulk_in_term_char: bool,
+    /// The capabilities of the USB488 subclass
+    pub usb488: Usb488Capabilities,
+}
+
+/// ### USB488 Capabilities
+///
+/// The capabilities of a device specific to the USB488 subclass.
+///
+#[derive(Clone, Debug)]
+pub struct Usb488Capabilities {
+    /// The version of the USB488 spec the device complies to, in BCD
+    pub bcd_version: u16,
+    /// The interface is IEEE 488.2 compliant
+    pub is_488_2: bool,
+    /// Accepts the REN_CONTROL, GO_TO_LOCAL and LOCAL_LOCKOUT requests
+    pub accepts_remote_local_requests: bool,
+    /// Accepts the TRIGGER bulk message
+    pub accepts_trigger: bool,
+    /// Understands all mandatory SCPI commands
+    pub is_scpi_compliant: bool,
+    /// Is SR1 capable (service request)
+    pub is_sr1_capable: bool,
+    /// Is RL1 capable (remote local)
+    pub is_rl1_capable: bool,
+    /// Is DT1 capable (device trigger)
+    pub is_dt1_capable: bool,
 }
@@ -0,0 +1,448 @@
+use std::time::{Duration, Instant};
+
+use rs_usbtmc::{Error, MockTransport, SimulatedDevice, Transport, UsbtmcClient};
+
+/// Connect a client to a mock answering the requests sent on connection
+fn connect(mock: &MockTransport) -> UsbtmcClient {
+    // GET_CAPABILITIES, INITIATE_CLEAR and CHECK_CLEAR_STATUS
+    let mut capabilities = vec![0x00; 0x18];
+    capabilities[0] = 0x01;
+    capabilities[5] = 0b0000_0001;
+    mock.push_control_in(capabilities);
+    mock.push_control_in([0x01]);
+    mock.push_control_in([0x01, 0x00]);
+
+    let endpoints = SimulatedDevice::new().endpoints();
+    UsbtmcClient::from_transport(mock.clone(), 0, endpoints).unwrap()
+}
+
+/// Build a DEV_DEP_MSG_IN transfer
+fn transfer(btag: u8, transfer_size: u32, data: &[u8]) -> Vec<u8> {
+    let mut transfer = vec![2, btag, !btag, 0];
+    transfer.extend_from_slice(&transfer_size.to_le_bytes());
+    transfer.extend_from_slice(&[0b0000_0001, 0, 0, 0]);
+    transfer.extend_from_slice(data);
+    while transfer.len() % 4 != 0 {
+        transfer.push(0x00);
+    }
+    transfer
+}
+
+#[test]
+fn response_keeps_trailing_nulls() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+
+    // the command uses bTag 1 and the request bTag 2
+    mock.push_bulk_in(transfer(2, 5, &[0x01, 0x00, 0x00, 0x00, 0x00]));
+
+    assert_eq!(
+        client.query_raw("DATA?").unwrap(),
+        [0x01, 0x00, 0x00, 0x00, 0x00]
+    );
+}
+
+#[test]
+fn mismatched_btag_is_rejected() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+
+    mock.push_bulk_in(transfer(7, 2, b"OK"));
+    // INITIATE_ABORT_BULK_IN finds nothing to abort
+    mock.push_control_in([0x80, 0x00]);
+
+    let err = client.query_raw("DATA?").unwrap_err();
+    assert!(matches!(
+        err.without_context(),
+        Error::BulkInMismatchedBTag {
+            expected: 2,
+            received: 7
+        }
+    ));
+    assert!(mock.cleared_halts().ends_with(&[0x82]));
+}
+
+#[test]
+fn unexpected_msg_id_is_rejected() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+
+    let mut response = transfer(2, 2, b"OK");
+    response[0] = 127;
+    mock.push_bulk_in(response);
+
+    let err = client.query_raw("DATA?").unwrap_err();
+    assert!(matches!(
+        err.without_context(),
+        Error::BulkInUnexpectedMsgId(127)
+    ));
+}
+
+#[test]
+fn short_header_is_rejected() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+
+    mock.push_bulk_in([2, 2, !2u8, 0]);
+
+    let err = client.query_raw("DATA?").unwrap_err();
+    assert!(matches!(err.without_context(), Error::BulkInShortHeader));
+}
+
+#[test]
+fn transfer_size_larger_than_requested_is_rejected() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+    client.set_transfer_size(16);
+
+    mock.push_bulk_in(transfer(2, 20, &[0x00; 20]));
+
+    let err = client.query_raw("DATA?").unwrap_err();
+    assert!(matches!(
+        err.without_context(),
+        Error::BulkInTransferSizeExceeded {
+            transfer_size: 20,
+            requested: 16
+        }
+    ));
+}
+
+#[test]
+fn short_transfer_is_rejected() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+
+    // the header announces 10 bytes but only 4 are sent
+    mock.push_bulk_in(transfer(2, 10, b"ABCD"));
+
+    let err = client.query_raw("DATA?").unwrap_err();
+    assert!(matches!(
+        err.without_context(),
+        Error::BulkInShortTransfer {
+            received: 4,
+            transfer_size: 10
+        }
+    ));
+}
+
+#[test]
+fn command_is_sent_in_one_aligned_transfer() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+
+    client.command("*RST").unwrap();
+    client.command("*CLS\n").unwrap();
+
+    assert_eq!(
+        mock.bulk_out(),
+        vec![
+            vec![1, 1, 254, 0, 4, 0, 0, 0, 1, 0, 0, 0, b'*', b'R', b'S', b'T'],
+            vec![1, 2, 253, 0, 5, 0, 0, 0, 1, 0, 0, 0, b'*', b'C', b'L', b'S', b'\n', 0, 0, 0,],
+        ]
+    );
+}
+
+#[test]
+fn transfer_longer_than_a_packet_is_padded_once() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+
+    // 12 + 61 bytes span two 64 bytes packets
+    let cmd = "A".repeat(61);
+    client.command(&cmd).unwrap();
+
+    let mut expected = vec![1, 1, 254, 0, 61, 0, 0, 0, 1, 0, 0, 0];
+    expected.extend_from_slice(cmd.as_bytes());
+    expected.extend_from_slice(&[0, 0, 0]);
+    assert_eq!(mock.bulk_out(), vec![expected]);
+}
+
+#[test]
+fn message_size_is_configurable() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+    client.set_message_size(3);
+
+    client.command("ABCDEFG").unwrap();
+
+    assert_eq!(
+        mock.bulk_out(),
+        vec![
+            vec![1, 1, 254, 0, 3, 0, 0, 0, 0, 0, 0, 0, b'A', b'B', b'C', 0],
+            vec![1, 2, 253, 0, 3, 0, 0, 0, 0, 0, 0, 0, b'D', b'E', b'F', 0],
+            vec![1, 3, 252, 0, 1, 0, 0, 0, 1, 0, 0, 0, b'G', 0, 0, 0],
+        ]
+    );
+}
+
+#[test]
+fn vendor_message_is_sent_in_one_aligned_transfer() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+
+    client.vendor_write(&[0xAA, 0xBB]).unwrap();
+
+    assert_eq!(
+        mock.bulk_out(),
+        vec![vec![
+            126, 1, 254, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0xAA, 0xBB, 0, 0
+        ]]
+    );
+}
+
+#[test]
+fn message_written_in_parts() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+
+    client.write_with_eom(b"DATA ", false).unwrap();
+    client.write_with_eom(&[0x00, 0xFF], true).unwrap();
+
+    assert_eq!(
+        mock.bulk_out(),
+        vec![
+            vec![1, 1, 254, 0, 5, 0, 0, 0, 0, 0, 0, 0, b'D', b'A', b'T', b'A', b' ', 0, 0, 0],
+            vec![1, 2, 253, 0, 2, 0, 0, 0, 1, 0, 0, 0, 0x00, 0xFF, 0, 0],
+        ]
+    );
+}
+
+#[test]
+fn empty_message_end_is_rejected() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+
+    // nothing to send without the end of message flag
+    client.write_with_eom(&[], false).unwrap();
+
+    // a transfer size of 0 can't end the message
+    let err = client.write_with_eom(&[], true).unwrap_err();
+    assert!(matches!(err.without_context(), Error::EmptyMessage));
+    assert!(mock.bulk_out().is_empty());
+}
+
+#[test]
+fn write_from_streams_messages() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+    client.set_message_size(4);
+
+    // only the first 6 bytes are sent
+    let reader = std::io::Cursor::new(vec![1, 2, 3, 4, 5, 6, 7, 8]);
+    client.write_from(reader, 6).unwrap();
+
+    assert_eq!(
+        mock.bulk_out(),
+        vec![
+            vec![1, 1, 254, 0, 4, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4],
+            vec![1, 2, 253, 0, 2, 0, 0, 0, 1, 0, 0, 0, 5, 6, 0, 0],
+        ]
+    );
+}
+
+#[test]
+fn write_from_short_reader_fails() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+
+    let reader = std::io::Cursor::new(vec![1, 2, 3]);
+    assert!(client.write_from(reader, 4).is_err());
+    assert!(mock.bulk_out().is_empty());
+}
+
+#[test]
+fn incomplete_block_is_rejected() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+
+    // the block announces 5 bytes but the device stops after 2
+    mock.push_bulk_in(transfer(2, 5, b"#15AB"));
+    mock.push_bulk_in(transfer(3, 0, &[]));
+
+    let err = client.query_block("DATA?").unwrap_err();
+    assert!(matches!(
+        err.without_context(),
+        Error::IncompleteBlock {
+            received: 2,
+            expected: 5
+        }
+    ));
+}
+
+#[test]
+fn errors_carry_context() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+
+    // nothing is queued so the read times out, then the abort finds no transfer
+    mock.push_control_in([0x80, 0x00]);
+
+    let err = client.query("*IDN?").unwrap_err();
+    assert!(err.is_timeout());
+    assert!(!err.is_disconnected());
+
+    let context = err.context().unwrap();
+    assert_eq!(context.operation, "read");
+    assert_eq!(context.command.as_deref(), Some("*IDN?"));
+    assert_eq!(context.btag, Some(2));
+    assert_eq!(context.endpoint, Some(0x82));
+
+    // each error of the chain is displayed once
+    let mut chain: Vec<String> = vec![err.to_string()];
+    let mut source = std::error::Error::source(&err);
+    while let Some(error) = source {
+        chain.push(error.to_string());
+        source = error.source();
+    }
+    assert_eq!(
+        chain,
+        [
+            "read failed (command \"*IDN?\", bTag 2, endpoint 0x82)",
+            "bulk in transfer aborted after an error",
+            "operation timed out",
+        ]
+    );
+}
+
+#[test]
+fn disconnection_is_reported() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+
+    mock.push_bulk_in_error(rusb::Error::NoDevice);
+
+    let err = client.query_raw("DATA?").unwrap_err();
+    assert!(err.is_disconnected());
+    assert!(matches!(err.without_context(), Error::Disconnected));
+}
+
+#[test]
+fn unexpected_status_is_reported() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+
+    // no transfer to abort, then STATUS_SPLIT_NOT_IN_PROGRESS in answer to INITIATE_CLEAR
+    mock.push_control_in([0x80, 0x00]);
+    mock.push_control_in([0x80, 0x00]);
+    mock.push_control_in([0x82]);
+
+    let err = client.clear().unwrap_err();
+    assert!(matches!(
+        err.without_context(),
+        Error::StatusUnexpectedFailure(0x82)
+    ));
+}
+
+#[test]
+fn empty_interrupt_queue_waits_for_timeout() {
+    let mock = MockTransport::new();
+
+    let start = Instant::now();
+    let err = mock
+        .read_interrupt(0x83, &mut [0x00; 2], Duration::from_millis(50))
+        .unwrap_err();
+    assert_eq!(err, rusb::Error::Timeout);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+/// Connect a client to a mock with an INTERRUPT IN endpoint of 8 bytes packets
+fn connect_with_interrupt_endpoint(mock: &MockTransport) -> UsbtmcClient {
+    let mut capabilities = vec![0x00; 0x18];
+    capabilities[0] = 0x01;
+    mock.push_control_in(capabilities);
+    mock.push_control_in([0x01]);
+    mock.push_control_in([0x01, 0x00]);
+
+    let device = SimulatedDevice::new();
+    device.set_interrupt_endpoint(true);
+    let mut endpoints = device.endpoints();
+    endpoints.interrupt_ep.as_mut().unwrap().max_packet_size = 8;
+    UsbtmcClient::from_transport(mock.clone(), 0, endpoints).unwrap()
+}
+
+#[test]
+fn listener_skips_vendor_notifications() {
+    let mock = MockTransport::new();
+    let client = connect_with_interrupt_endpoint(&mock);
+
+    // bNotify1 bit 7 clear, filling a whole packet
+    mock.push_interrupt_in([0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]);
+    mock.push_interrupt_in([0x81, 0x40]);
+
+    let stb = client
+        .wait_for_srq(Duration::from_secs(1))
+        .unwrap()
+        .unwrap();
+    assert_eq!(stb.bits(), 0x40);
+}
+
+#[test]
+fn status_byte_read_skips_vendor_notifications() {
+    let mock = MockTransport::new();
+    let client = connect_with_interrupt_endpoint(&mock);
+
+    // READ_STATUS_BYTE with bTag 2, answered on the interrupt endpoint
+    mock.push_control_in([0x01, 0x02, 0x00]);
+    mock.push_interrupt_in([0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]);
+    mock.push_interrupt_in([0x82, 0x10]);
+
+    assert_eq!(client.read_ieee488_status_byte().unwrap().bits(), 0x10);
+}
+
+#[test]
+fn status_byte_read_skips_late_notifications() {
+    let mock = MockTransport::new();
+    let client = connect_with_interrupt_endpoint(&mock);
+    client.set_timeout(Duration::from_millis(100));
+
+    // READ_STATUS_BYTE with bTag 2, not answered in time
+    mock.push_control_in([0x01, 0x02, 0x00]);
+    let err = client.read_ieee488_status_byte().unwrap_err();
+    assert!(err.is_timeout());
+
+    // the late answer with bTag 2 comes before the one of the next request
+    mock.push_control_in([0x01, 0x03, 0x00]);
+    mock.push_interrupt_in([0x82, 0x10]);
+    mock.push_interrupt_in([0x83, 0x20]);
+    assert_eq!(client.read_ieee488_status_byte().unwrap().bits(), 0x20);
+
+    mock.push_control_in([0x01, 0x04, 0x00]);
+    mock.push_interrupt_in([0x84, 0x30]);
+    assert_eq!(client.read_ieee488_status_byte().unwrap().bits(), 0x30);
+}
+
+/// Build a VENDOR_SPECIFIC_IN transfer
+fn vendor_transfer(btag: u8, data: &[u8]) -> Vec<u8> {
+    let mut transfer = transfer(btag, data.len() as u32, data);
+    transfer[0] = 127;
+    transfer[8] = 0x00;
+    transfer
+}
+
+#[test]
+fn vendor_read_keeps_data_when_last_request_is_not_answered() {
+    let mock = MockTransport::new();
+    let client = connect(&mock);
+    client.set_transfer_size(4);
+
+    // the data is a multiple of the transfer size, so the third request gets no answer
+    mock.push_bulk_in(vendor_transfer(1, &[1, 2, 3, 4]));
+    mock.push_bulk_in(vendor_transfer(2, &[5, 6, 7, 8]));
+    // INITIATE_ABORT_BULK_IN of the third request finds nothing to abort
+    mock.push_control_in([0x80, 0x00]);
+
+    assert_eq!(client.vendor_read().unwrap(), [1, 2, 3, 4, 5, 6, 7, 8]);
+
+    let requests = mock.bulk_out();
+    assert_eq!(requests.len(), 3);
+    assert!(requests
+        .iter()
+        .all(|r| r[0] == 127 && r[4..8] == 4u32.to_le_bytes()));
+
+    // INITIATE_ABORT_BULK_IN for the bTag of the unanswered request
+    let abort = mock.control_requests().pop().unwrap();
+    assert_eq!(abort.request, 3);
+    assert_eq!(abort.value, 3);
+    assert_eq!(abort.index, 0x82);
+    assert!(mock.cleared_halts().ends_with(&[0x82]));
+}
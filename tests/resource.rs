@@ -0,0 +1,74 @@
+use rs_usbtmc::{DeviceAddr, DeviceId, DeviceInfo, Error, VisaResource};
+
+#[test]
+fn resource_string_is_parsed() {
+    let resource: VisaResource = "USB0::0x0957::0x1798::MY12345678::INSTR".parse().unwrap();
+
+    assert_eq!(
+        resource,
+        VisaResource {
+            board: 0,
+            vendor_id: 0x0957,
+            product_id: 0x1798,
+            serial_number: Some("MY12345678".to_string()),
+            interface_number: None,
+        }
+    );
+    assert_eq!(
+        resource.to_string(),
+        "USB0::0x0957::0x1798::MY12345678::INSTR"
+    );
+}
+
+#[test]
+fn resource_string_variants() {
+    // decimal identifiers, interface number and no resource class
+    let resource: VisaResource = "usb1::2391::6040::MY12345678::2".parse().unwrap();
+    assert_eq!(resource.board, 1);
+    assert_eq!(resource.vendor_id, 0x0957);
+    assert_eq!(resource.product_id, 0x1798);
+    assert_eq!(resource.interface_number, Some(2));
+    assert_eq!(
+        resource.to_string(),
+        "USB1::0x0957::0x1798::MY12345678::2::INSTR"
+    );
+
+    // no board number nor serial number
+    let resource: VisaResource = "USB::0x1AB1::0x04CE::INSTR".parse().unwrap();
+    assert_eq!(resource.board, 0);
+    assert_eq!(resource.serial_number, None);
+    assert_eq!(resource.to_string(), "USB0::0x1AB1::0x04CE::INSTR");
+}
+
+#[test]
+fn invalid_resource_strings_are_rejected() {
+    for resource in [
+        "",
+        "TCPIP0::192.168.0.1::INSTR",
+        "USB0::0x0957::INSTR",
+        "USB0::0x10000::0x1798::INSTR",
+        "USB0::0x0957::0x1798::::INSTR",
+        "USB0::0x0957::0x1798::MY12345678::X::INSTR",
+        "USB0::0x0957::0x1798::MY12345678::0::1::INSTR",
+    ] {
+        let err = resource.parse::<VisaResource>().unwrap_err();
+        assert!(matches!(err, Error::InvalidResource(r) if r == resource));
+    }
+}
+
+#[test]
+fn device_info_round_trips_through_resource_string() {
+    let info = DeviceInfo {
+        id: DeviceId {
+            vendor_id: 0x0957,
+            product_id: 0x1798,
+        },
+        address: DeviceAddr { bus: 1, device: 4 },
+        manufacturer: Some("Keysight Technologies".to_string()),
+        product: Some("DSO-X 3034A".to_string()),
+        serial_number: Some("MY12345678".to_string()),
+    };
+
+    let resource: VisaResource = info.to_string().parse().unwrap();
+    assert_eq!(resource, info.resource());
+}
@@ -0,0 +1,950 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rs_usbtmc::{
+    ByteOrder, Error, EventStatus, OperationStatus, QuestionableStatus, ReadEnd, SimulatedDevice,
+    StatusByte, Transport, UsbtmcClient,
+};
+
+fn connect(device: &SimulatedDevice) -> UsbtmcClient {
+    UsbtmcClient::from_transport(device.clone(), 0, device.endpoints())
+        .expect("failed to connect to the simulated device")
+}
+
+#[test]
+fn connect_sends_capabilities_and_clear() {
+    let device = SimulatedDevice::new();
+    let _client = connect(&device);
+
+    // GET_CAPABILITIES, INITIATE_CLEAR, CHECK_CLEAR_STATUS
+    assert_eq!(device.control_requests(), vec![7, 5, 6]);
+    assert_eq!(device.cleared_halts(), vec![0x01, 0x82]);
+}
+
+#[test]
+fn command_is_framed_with_header_and_padding() {
+    let device = SimulatedDevice::new();
+    let client = connect(&device);
+
+    client.command("*RST").unwrap();
+
+    assert_eq!(device.messages(), vec![b"*RST".to_vec()]);
+    let transfers = device.transfers();
+    let header = &transfers[0][..12];
+    assert_eq!(header[0], 1);
+    assert_eq!(header[1], !header[2]);
+    assert_eq!(&header[4..8], &4u32.to_le_bytes());
+    assert_eq!(header[8], 0b0000_0001);
+}
+
+#[test]
+fn query_returns_response() {
+    let device = SimulatedDevice::new();
+    device.on_query("*IDN?", |_| b"SIM,USBTMC,0,1.0\n".to_vec());
+    let client = connect(&device);
+
+    assert_eq!(client.query("*IDN?").unwrap(), "SIM,USBTMC,0,1.0");
+}
+
+#[test]
+fn query_raw_keeps_trailing_null_bytes() {
+    let device = SimulatedDevice::new();
+    device.set_capabilities(0b0000_0100, 0b0000_0000);
+    device.on_query("DATA?", |_| vec![0x01, 0x02, 0x03, 0x00, 0x00]);
+    let client = connect(&device);
+
+    assert_eq!(
+        client.query_raw("DATA?").unwrap(),
+        vec![0x01, 0x02, 0x03, 0x00, 0x00]
+    );
+}
+
+#[test]
+fn query_raw_handles_all_payload_alignments() {
+    let device = SimulatedDevice::new();
+    for n in 1..=8 {
+        device.on_query(&format!("DATA{}?", n), move |_| vec![b'x'; n]);
+    }
+    let client = connect(&device);
+
+    for n in 1..=8 {
+        let resp = client.query_raw(&format!("DATA{}?", n)).unwrap();
+        assert_eq!(resp, vec![b'x'; n]);
+    }
+}
+
+#[test]
+fn query_spanning_several_transfers() {
+    let device = SimulatedDevice::new();
+    let response: Vec<u8> = (0..1000u32).map(|v| (v % 251) as u8 + 1).collect();
+    let expected = response.clone();
+    device.set_capabilities(0b0000_0100, 0b0000_0000);
+    device.on_query("CURVE?", move |_| response.clone());
+    let client = connect(&device);
+
+    assert_eq!(client.query_raw("CURVE?").unwrap(), expected);
+}
+
+#[test]
+fn large_response_uses_few_requests() {
+    let device = SimulatedDevice::new();
+    device.set_max_packet_size(512);
+    let response: Vec<u8> = (0..3_000_001u32).map(|v| v as u8).collect();
+    let expected = response.clone();
+    device.on_query("CURVE?", move |_| response.clone());
+    let client = connect(&device);
+    client.set_term_char(None);
+
+    assert_eq!(client.query_raw("CURVE?").unwrap(), expected);
+
+    // the command and three 1 MiB requests
+    let requests = device.transfers().iter().filter(|t| t[0] == 2).count();
+    assert_eq!(requests, 3);
+}
+
+#[test]
+fn short_response_is_not_allocated_the_transfer_size() {
+    let device = SimulatedDevice::new();
+    device.on_query("*IDN?", |_| b"SIM\n".to_vec());
+    device.on_query("DATA?", |_| vec![0x01; 100]);
+    let client = connect(&device);
+
+    assert!(client.query_raw("*IDN?").unwrap().capacity() < 64);
+
+    client.command("DATA?").unwrap();
+    assert!(client.read_chunk(usize::MAX).unwrap().data.capacity() < 256);
+}
+
+#[test]
+fn transfer_size_is_configurable() {
+    let device = SimulatedDevice::new();
+    let response: Vec<u8> = (0..1001u32).map(|v| (v % 251) as u8 + 1).collect();
+    let expected = response.clone();
+    device.on_query("CURVE?", move |_| response.clone());
+    let client = connect(&device);
+    client.set_term_char(None);
+    client.set_transfer_size(100);
+
+    assert_eq!(client.query_raw("CURVE?").unwrap(), expected);
+
+    let requests = device.transfers().iter().filter(|t| t[0] == 2).count();
+    assert_eq!(requests, 11);
+}
+
+#[test]
+fn query_into_caller_buffer() {
+    let device = SimulatedDevice::new();
+    let response: Vec<u8> = (0..1001u32).map(|v| (v % 251) as u8 + 1).collect();
+    let expected = response.clone();
+    device.on_query("CURVE?", move |_| response.clone());
+    let client = connect(&device);
+    client.set_term_char(None);
+
+    // the buffer holds the whole response
+    let mut buf = vec![0x00; 2048];
+    let (len, end) = client.query_into("CURVE?", &mut buf).unwrap();
+    assert_eq!(&buf[..len], expected);
+    assert_eq!(end, ReadEnd::EndOfMessage);
+
+    // the response is read in several parts
+    let mut buf = vec![0x00; 601];
+    let (len, end) = client.query_into("CURVE?", &mut buf).unwrap();
+    assert_eq!((len, end), (601, ReadEnd::TransferSize));
+    assert_eq!(buf, expected[..601]);
+    let (len, end) = client.read_into(&mut buf).unwrap();
+    assert_eq!((len, end), (400, ReadEnd::EndOfMessage));
+    assert_eq!(buf[..len], expected[601..]);
+}
+
+#[test]
+fn long_command_is_split_in_several_messages() {
+    let device = SimulatedDevice::new();
+    let client = connect(&device);
+
+    let cmd = "A".repeat(20_000);
+    client.command(&cmd).unwrap();
+
+    assert_eq!(device.messages(), vec![cmd.into_bytes()]);
+}
+
+#[test]
+fn binary_upload_is_reassembled() {
+    let device = SimulatedDevice::new();
+    let client = connect(&device);
+    client.set_message_size(1000);
+
+    let table: Vec<u8> = (0..10_000u32).map(|v| v as u8).collect();
+    let mut message = b"DATA:ARB ".to_vec();
+    message.extend_from_slice(&table);
+    client.write_with_eom(b"DATA:ARB ", false).unwrap();
+    client
+        .write_from(table.as_slice(), table.len() as u64)
+        .unwrap();
+
+    assert_eq!(device.messages(), vec![message]);
+}
+
+#[test]
+fn btag_changes_on_each_message() {
+    let device = SimulatedDevice::new();
+    let client = connect(&device);
+
+    for _ in 0..300 {
+        client.command("*CLS").unwrap();
+    }
+
+    let btags: Vec<u8> = device.transfers().iter().map(|t| t[1]).collect();
+    assert!(btags.iter().all(|btag| *btag != 0));
+    assert!(btags.windows(2).all(|w| w[0] != w[1]));
+}
+
+#[test]
+fn status_byte_without_interrupt_endpoint() {
+    let device = SimulatedDevice::new();
+    device.set_status_byte(0b0100_0000);
+    let client = connect(&device);
+
+    assert_eq!(
+        client.read_ieee488_status_byte().unwrap().bits(),
+        0b0100_0000
+    );
+}
+
+#[test]
+fn usb488_capabilities_are_parsed() {
+    let device = SimulatedDevice::new();
+    device.set_usb488_capabilities(0b0000_0111, 0b0000_1101);
+    let client = connect(&device);
+
+    let usb488 = &client.capabilities().usb488;
+    assert_eq!(usb488.bcd_version, 0x0100);
+    assert!(usb488.is_488_2);
+    assert!(usb488.accepts_remote_local_requests);
+    assert!(usb488.accepts_trigger);
+    assert!(usb488.is_scpi_compliant);
+    assert!(usb488.is_sr1_capable);
+    assert!(!usb488.is_rl1_capable);
+    assert!(usb488.is_dt1_capable);
+}
+
+#[test]
+fn remote_local_requests_require_capability() {
+    let device = SimulatedDevice::new();
+    let client = connect(&device);
+
+    assert!(client.local_lockout().is_err());
+    assert!(client.go_to_local().is_err());
+    assert!(!device.control_requests().contains(&162));
+}
+
+#[test]
+fn remote_local_requests() {
+    let device = SimulatedDevice::new();
+    device.set_usb488_capabilities(0b0000_0110, 0b0000_0000);
+    let client = connect(&device);
+
+    client.ren_control(true).unwrap();
+    client.local_lockout().unwrap();
+    client.go_to_local().unwrap();
+    client.ren_control(false).unwrap();
+
+    assert!(device.control_requests().ends_with(&[160, 162, 161, 160]));
+}
+
+#[test]
+fn trigger_uses_trigger_message_when_accepted() {
+    let device = SimulatedDevice::new();
+    device.set_usb488_capabilities(0b0000_0001, 0b0000_0001);
+    let client = connect(&device);
+
+    client.trigger().unwrap();
+
+    assert_eq!(device.triggers(), 1);
+    assert!(device.messages().is_empty());
+    let header = &device.transfers()[0];
+    assert_eq!(header.len(), 12);
+    assert_eq!(header[0], 128);
+    assert_eq!(header[1], !header[2]);
+}
+
+#[test]
+fn trigger_needs_interface_to_accept_trigger_message() {
+    let device = SimulatedDevice::new();
+    // DT1 capable, but the interface doesn't accept the TRIGGER message
+    device.set_usb488_capabilities(0b0000_0000, 0b0000_0001);
+    let client = connect(&device);
+
+    client.trigger().unwrap();
+
+    assert_eq!(device.triggers(), 0);
+    assert_eq!(device.messages(), vec![b"*TRG".to_vec()]);
+}
+
+#[test]
+fn trigger_falls_back_to_trg_command() {
+    let device = SimulatedDevice::new();
+    let client = connect(&device);
+
+    client.trigger().unwrap();
+
+    assert_eq!(device.triggers(), 0);
+    assert_eq!(device.messages(), vec![b"*TRG".to_vec()]);
+}
+
+#[test]
+fn status_byte_with_interrupt_endpoint() {
+    let device = SimulatedDevice::new();
+    device.set_interrupt_endpoint(true);
+    device.set_status_byte(0b0000_0100);
+    let client = connect(&device);
+
+    for _ in 0..200 {
+        assert_eq!(
+            client.read_ieee488_status_byte().unwrap().bits(),
+            0b0000_0100
+        );
+    }
+}
+
+#[test]
+fn status_byte_reports_message_available() {
+    let device = SimulatedDevice::new();
+    let client = connect(&device);
+    device.push_response(b"1\n".to_vec());
+
+    let stb = client.read_ieee488_status_byte().unwrap();
+    assert!(stb.message_available());
+    assert!(!stb.request_service());
+}
+
+#[test]
+fn status_byte_retries_when_interrupt_fifo_is_full() {
+    let device = SimulatedDevice::new();
+    device.set_interrupt_endpoint(true);
+    let client = connect(&device);
+
+    // the pending SRQ notification fills the FIFO of the device
+    device.request_service(0b0000_0001);
+    let stb = client.read_ieee488_status_byte().unwrap();
+
+    assert_eq!(stb.bits(), 0b0100_0001);
+    assert!(stb.request_service());
+    assert_eq!(
+        device
+            .control_requests()
+            .iter()
+            .filter(|r| **r == 128)
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn wait_for_srq_requires_interrupt_endpoint() {
+    let device = SimulatedDevice::new();
+    let client = connect(&device);
+
+    let err = client.wait_for_srq(Duration::from_millis(10)).unwrap_err();
+    assert!(matches!(
+        err.without_context(),
+        Error::InterruptEndpointNotFound
+    ));
+    assert_eq!(err.context().unwrap().operation, "start SRQ listener");
+}
+
+#[test]
+fn control_request_errors_carry_context() {
+    let device = SimulatedDevice::new();
+    device.set_capabilities(0b0000_0000, 0b0000_0000);
+    let client = connect(&device);
+
+    let err = client.ren_control(true).unwrap_err();
+    assert!(matches!(
+        err.without_context(),
+        Error::RemoteLocalNotSupported
+    ));
+    assert_eq!(err.context().unwrap().operation, "REN_CONTROL");
+
+    let err = client.pulse_indicator().unwrap_err();
+    assert_eq!(err.to_string(), "INDICATOR_PULSE failed");
+    assert_eq!(
+        std::error::Error::source(&err).unwrap().to_string(),
+        "device does not accept the INDICATOR_PULSE request"
+    );
+}
+
+#[test]
+fn wait_for_srq_times_out_without_request() {
+    let device = SimulatedDevice::new();
+    device.set_interrupt_endpoint(true);
+    let client = connect(&device);
+
+    assert_eq!(
+        client.wait_for_srq(Duration::from_millis(50)).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn srq_is_delivered_to_waiter_channel_and_callback() {
+    let device = SimulatedDevice::new();
+    device.set_interrupt_endpoint(true);
+    let client = connect(&device);
+
+    let called = Arc::new(AtomicU8::new(0));
+    let callback_called = called.clone();
+    client
+        .on_srq(move |stb| callback_called.store(stb.bits(), Ordering::SeqCst))
+        .unwrap();
+    let receiver = client.srq_receiver().unwrap();
+
+    device.request_service(0b0010_0000);
+
+    let stb = client.wait_for_srq(Duration::from_secs(1)).unwrap();
+    assert_eq!(stb.map(|stb| stb.bits()), Some(0b0110_0000));
+    assert_eq!(
+        receiver
+            .recv_timeout(Duration::from_secs(1))
+            .unwrap()
+            .bits(),
+        0b0110_0000
+    );
+    // the callback runs on the dispatcher thread after the waiters are woken up
+    for _ in 0..100 {
+        if called.load(Ordering::SeqCst) != 0 {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(called.load(Ordering::SeqCst), 0b0110_0000);
+}
+
+#[test]
+fn srq_callback_can_read_status_byte() {
+    let device = SimulatedDevice::new();
+    device.set_interrupt_endpoint(true);
+    let client = Arc::new(connect(&device));
+
+    // the callback reads the status byte, answered on the endpoint it was notified on
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let weak_client = Arc::downgrade(&client);
+    client
+        .on_srq(move |_| {
+            if let Some(client) = weak_client.upgrade() {
+                let _ = sender.send(client.read_ieee488_status_byte());
+            }
+        })
+        .unwrap();
+
+    device.request_service(0b0010_0000);
+
+    let stb = receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+    assert_eq!(stb.unwrap().bits(), 0b0110_0000);
+}
+
+#[test]
+fn status_byte_is_demultiplexed_from_srq() {
+    let device = SimulatedDevice::new();
+    device.set_interrupt_endpoint(true);
+    let client = connect(&device);
+    let receiver = client.srq_receiver().unwrap();
+
+    device.request_service(0b0000_0001);
+    assert_eq!(
+        client.read_ieee488_status_byte().unwrap().bits(),
+        0b0100_0001
+    );
+    assert_eq!(
+        receiver
+            .recv_timeout(Duration::from_secs(1))
+            .unwrap()
+            .bits(),
+        0b0100_0001
+    );
+    assert!(receiver.try_recv().is_err());
+}
+
+#[test]
+fn vendor_query_round_trip() {
+    let device = SimulatedDevice::new();
+    device.on_vendor(|data| data.iter().rev().copied().collect());
+    let client = connect(&device);
+
+    let response = client.vendor_query(&[0x01, 0x02, 0x03]).unwrap();
+
+    assert_eq!(response, vec![0x03, 0x02, 0x01]);
+    assert_eq!(device.vendor_messages(), vec![vec![0x01, 0x02, 0x03]]);
+    assert!(device.messages().is_empty());
+    assert_eq!(device.transfers()[0][0], 126);
+}
+
+#[test]
+fn vendor_read_reassembles_several_transfers() {
+    let device = SimulatedDevice::new();
+    let data: Vec<u8> = (0..200u8).collect();
+    let response = data.clone();
+    device.on_vendor(move |_| response.clone());
+    let client = connect(&device);
+
+    client.vendor_write(b"DUMP").unwrap();
+
+    assert_eq!(client.vendor_read().unwrap(), data);
+}
+
+#[test]
+fn timed_out_query_is_aborted() {
+    let device = SimulatedDevice::new();
+    device.on_query("*IDN?", |_| b"SIM,USBTMC,0,1.0\n".to_vec());
+    let client = connect(&device);
+
+    let error = client.query("MEAS?").unwrap_err();
+    assert!(matches!(
+        error.without_context(),
+        Error::BulkInTransferAborted(_)
+    ));
+
+    // INITIATE_ABORT_BULK_IN, CHECK_ABORT_BULK_IN_STATUS
+    assert!(device.control_requests().ends_with(&[3, 4]));
+    assert_eq!(device.cleared_halts().last(), Some(&0x82));
+
+    // the connection is usable again
+    assert_eq!(client.query("*IDN?").unwrap(), "SIM,USBTMC,0,1.0");
+}
+
+#[test]
+fn abort_out_without_transfer_in_progress_fails() {
+    let device = SimulatedDevice::new();
+    let client = connect(&device);
+
+    client.command("*RST").unwrap();
+
+    assert!(client.abort_out().is_err());
+    assert!(device.control_requests().ends_with(&[1]));
+}
+
+#[test]
+fn clear_drains_bulk_in_fifo() {
+    let device = SimulatedDevice::new();
+    device.on_query("*IDN?", |_| b"SIM,USBTMC,0,1.0\n".to_vec());
+    let client = connect(&device);
+
+    // leave a response in the Bulk-IN FIFO of the device
+    device.push_response(b"STALE\n".to_vec());
+    let request = [2, 200, !200, 0, 64, 0, 0, 0, 0, 0, 0, 0];
+    device
+        .write_bulk(0x01, &request, Duration::from_secs(1))
+        .unwrap();
+
+    client.clear().unwrap();
+
+    // INITIATE_CLEAR, CHECK_CLEAR_STATUS (pending), CHECK_CLEAR_STATUS (success)
+    assert!(device.control_requests().ends_with(&[5, 6, 6]));
+    assert!(device.cleared_halts().ends_with(&[0x01, 0x82]));
+    assert_eq!(client.query("*IDN?").unwrap(), "SIM,USBTMC,0,1.0");
+}
+
+#[test]
+fn pulse_indicator() {
+    let device = SimulatedDevice::new();
+    let client = connect(&device);
+
+    client.pulse_indicator().unwrap();
+
+    assert!(device.control_requests().ends_with(&[64]));
+}
+
+#[test]
+fn pulse_indicator_requires_capability() {
+    let device = SimulatedDevice::new();
+    device.set_capabilities(0b0000_0000, 0b0000_0001);
+    let client = connect(&device);
+
+    assert!(client.pulse_indicator().is_err());
+    assert!(!device.control_requests().contains(&64));
+}
+
+#[test]
+fn read_stops_on_term_char() {
+    let device = SimulatedDevice::new();
+    device.on_query("READ?", |_| b"A\rB\n".to_vec());
+    let client = connect(&device);
+
+    let resp = client.query_with_term_char("READ?", Some(b'\r')).unwrap();
+
+    assert_eq!(resp.data, b"A\r");
+    assert_eq!(resp.end, ReadEnd::TermChar);
+}
+
+#[test]
+fn read_binary_without_term_char() {
+    let device = SimulatedDevice::new();
+    device.on_query("DATA?", |_| vec![0x01, 0x0A, 0x02, 0x0A, 0x03]);
+    let client = connect(&device);
+
+    client.set_term_char(None);
+    assert_eq!(
+        client.query_raw("DATA?").unwrap(),
+        [0x01, 0x0A, 0x02, 0x0A, 0x03]
+    );
+
+    let resp = client.query_with_term_char("DATA?", None).unwrap();
+    assert_eq!(resp.end, ReadEnd::EndOfMessage);
+}
+
+#[test]
+fn term_char_ignored_without_device_support() {
+    let device = SimulatedDevice::new();
+    device.set_capabilities(0b0000_0100, 0b0000_0000);
+    device.on_query("DATA?", |_| b"A\nB\n".to_vec());
+    let client = connect(&device);
+
+    assert_eq!(client.query_raw("DATA?").unwrap(), b"A\nB\n");
+}
+
+#[test]
+fn read_without_command() {
+    let device = SimulatedDevice::new();
+    let client = connect(&device);
+
+    // the device has output queued, for example after a trigger
+    device.push_response(b"1.234\n".to_vec());
+    assert_eq!(client.read().unwrap(), "1.234");
+
+    device.push_response(vec![0x00, 0x01, 0x02]);
+    client.set_term_char(None);
+    assert_eq!(client.read_raw().unwrap(), [0x00, 0x01, 0x02]);
+    assert_eq!(device.messages(), Vec::<Vec<u8>>::new());
+}
+
+#[test]
+fn standalone_read_with_term_char() {
+    let device = SimulatedDevice::new();
+    let client = connect(&device);
+
+    // binary data read without changing the termination character of the client
+    device.push_response(vec![0x01, 0x0A, 0x02]);
+    let resp = client.read_with_term_char(None).unwrap();
+    assert_eq!(resp.data, [0x01, 0x0A, 0x02]);
+    assert_eq!(resp.end, ReadEnd::EndOfMessage);
+
+    device.push_response(vec![0x01, 0x0A, 0x02]);
+    let mut buf = [0x00; 8];
+    let (len, end) = client.read_into_with_term_char(&mut buf, None).unwrap();
+    assert_eq!(&buf[..len], [0x01, 0x0A, 0x02]);
+    assert_eq!(end, ReadEnd::EndOfMessage);
+
+    // the client setting is left as is
+    device.push_response(b"A\nB\n".to_vec());
+    assert_eq!(client.read_raw().unwrap(), b"A\n");
+}
+
+#[test]
+fn read_response_in_chunks() {
+    let device = SimulatedDevice::new();
+    let waveform: Vec<u8> = (0..250u32).map(|v| v as u8).collect();
+    let expected = waveform.clone();
+    device.on_query(":WAV:DATA?", move |_| waveform.clone());
+    let client = connect(&device);
+    client.set_term_char(None);
+
+    client.command(":WAV:DATA?").unwrap();
+    let mut data = Vec::new();
+    loop {
+        let chunk = client.read_chunk(100).unwrap();
+        assert!(chunk.data.len() <= 100);
+        data.extend_from_slice(&chunk.data);
+        if chunk.end == ReadEnd::EndOfMessage {
+            break;
+        }
+        assert_eq!(chunk.end, ReadEnd::TransferSize);
+    }
+
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn stream_write_and_read() {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let device = SimulatedDevice::new();
+    device.on_query("*IDN?", |_| b"SIM,USBTMC,0,1.0\n".to_vec());
+    let waveform: Vec<u8> = (0..5000u32).map(|v| v as u8).collect();
+    let expected = waveform.clone();
+    device.on_query("CURVE?", move |_| waveform.clone());
+    let client = connect(&device);
+
+    // the writes are sent as a single message on flush
+    let mut stream = client.stream();
+    write!(stream, "*IDN").unwrap();
+    stream.write_all(b"?").unwrap();
+    stream.flush().unwrap();
+    assert_eq!(device.messages(), vec![b"*IDN?".to_vec()]);
+
+    let mut line = String::new();
+    BufReader::new(&mut stream).read_line(&mut line).unwrap();
+    assert_eq!(line, "SIM,USBTMC,0,1.0\n");
+
+    // binary data is read until the end of the message
+    client.set_term_char(None);
+    let mut stream = client.stream();
+    stream.write_all(b"CURVE?").unwrap();
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).unwrap();
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn query_definite_length_block() {
+    let device = SimulatedDevice::new();
+    let waveform: Vec<u8> = (0..1234u32).map(|v| v as u8).collect();
+    let expected = waveform.clone();
+    device.on_query("CURVE?", move |_| {
+        let mut resp = b"#41234".to_vec();
+        resp.extend_from_slice(&waveform);
+        resp.push(b'\n');
+        resp
+    });
+    let client = connect(&device);
+
+    // the block contains newlines but is read whole
+    assert_eq!(client.query_block("CURVE?").unwrap(), expected);
+}
+
+#[test]
+fn query_block_across_messages() {
+    let device = SimulatedDevice::new();
+    let client = connect(&device);
+
+    device.push_response(b"#210ABCD".to_vec());
+    device.push_response(b"EFGHIJ\n".to_vec());
+
+    assert_eq!(client.query_block("DATA?").unwrap(), b"ABCDEFGHIJ");
+}
+
+#[test]
+fn query_indefinite_length_block() {
+    let device = SimulatedDevice::new();
+    device.on_query("DATA?", |_| b"#0\x01\x0A\x02\n".to_vec());
+    let client = connect(&device);
+
+    assert_eq!(client.query_block("DATA?").unwrap(), [0x01, 0x0A, 0x02]);
+}
+
+#[test]
+fn query_block_malformed_header() {
+    let device = SimulatedDevice::new();
+    device.on_query("NOBLOCK?", |_| b"1.234\n".to_vec());
+    device.on_query("BADLEN?", |_| b"#3A12xyz\n".to_vec());
+    let client = connect(&device);
+
+    let err = client.query_block("NOBLOCK?").unwrap_err();
+    assert!(matches!(err.without_context(), Error::InvalidBlockHeader));
+    let err = client.query_block("BADLEN?").unwrap_err();
+    assert!(matches!(err.without_context(), Error::InvalidBlockHeader));
+}
+
+#[test]
+fn query_binary_values() {
+    let device = SimulatedDevice::new();
+    device.on_query("CURV:BE?", |_| {
+        b"#18\x00\x01\xFF\xFE\x7F\xFF\x80\x00\n".to_vec()
+    });
+    device.on_query("CURV:LE?", |_| {
+        let mut resp = b"#18".to_vec();
+        resp.extend_from_slice(&1.5f32.to_le_bytes());
+        resp.extend_from_slice(&(-2.25f32).to_le_bytes());
+        resp.push(b'\n');
+        resp
+    });
+    device.on_query("CURV:ODD?", |_| b"#13\x00\x01\x02\n".to_vec());
+    let client = connect(&device);
+
+    let values: Vec<i16> = client
+        .query_binary_values("CURV:BE?", ByteOrder::BigEndian)
+        .unwrap();
+    assert_eq!(values, [1, -2, i16::MAX, i16::MIN]);
+
+    let values: Vec<u8> = client
+        .query_binary_values("CURV:BE?", ByteOrder::BigEndian)
+        .unwrap();
+    assert_eq!(values, [0x00, 0x01, 0xFF, 0xFE, 0x7F, 0xFF, 0x80, 0x00]);
+
+    let values: Vec<f32> = client
+        .query_binary_values("CURV:LE?", ByteOrder::LittleEndian)
+        .unwrap();
+    assert_eq!(values, [1.5, -2.25]);
+
+    let err = client
+        .query_binary_values::<i16>("CURV:ODD?", ByteOrder::BigEndian)
+        .unwrap_err();
+    assert!(matches!(
+        err.without_context(),
+        Error::InvalidBlockLength {
+            length: 3,
+            element_size: 2
+        }
+    ));
+}
+
+#[test]
+fn status_byte_bits_are_decoded() {
+    let device = SimulatedDevice::new();
+    let client = connect(&device);
+
+    device.set_status_byte(0b1010_1100);
+    let stb = client.read_ieee488_status_byte().unwrap();
+
+    assert!(stb.error_available());
+    assert!(stb.questionable_summary());
+    assert!(stb.event_status());
+    assert!(stb.operation_summary());
+    assert!(!stb.message_available());
+    assert!(!stb.request_service());
+    assert!(stb.contains(StatusByte::ESB | StatusByte::OPER));
+}
+
+#[test]
+fn standard_status_registers() {
+    let device = SimulatedDevice::new();
+    device.on_query("*ESR?", |_| b"+36\n".to_vec());
+    device.on_query("*STB?", |_| b"96\n".to_vec());
+    device.on_query("STAT:OPER:COND?", |_| b"+16\n".to_vec());
+    device.on_query("STAT:OPER:EVEN?", |_| b"+8224\n".to_vec());
+    device.on_query("STAT:QUES:COND?", |_| b"+0\n".to_vec());
+    device.on_query("STAT:QUES:EVEN?", |_| b"+257\n".to_vec());
+    let client = connect(&device);
+
+    let esr = client.read_event_status().unwrap();
+    assert_eq!(esr, EventStatus::CME | EventStatus::QYE);
+    assert!(esr.has_error());
+    assert!(!esr.operation_complete());
+
+    let stb = client.query_status_byte().unwrap();
+    assert!(stb.master_summary() && stb.event_status());
+
+    assert_eq!(
+        client.read_operation_condition().unwrap(),
+        OperationStatus::MEASURING
+    );
+    assert_eq!(
+        client.read_operation_event().unwrap(),
+        OperationStatus::INSTRUMENT_SUMMARY | OperationStatus::WAITING_FOR_TRIGGER
+    );
+    assert!(client.read_questionable_condition().unwrap().is_empty());
+    assert_eq!(
+        client.read_questionable_event().unwrap(),
+        QuestionableStatus::VOLTAGE | QuestionableStatus::CALIBRATION
+    );
+}
+
+#[test]
+fn status_enable_masks() {
+    let device = SimulatedDevice::new();
+    let client = connect(&device);
+
+    client
+        .set_event_status_enable(EventStatus::OPC | EventStatus::EXE)
+        .unwrap();
+    client
+        .set_service_request_enable(StatusByte::ESB | StatusByte::MAV)
+        .unwrap();
+
+    assert_eq!(
+        device.messages(),
+        vec![b"*ESE 17".to_vec(), b"*SRE 48".to_vec()]
+    );
+}
+
+#[test]
+fn invalid_register_response() {
+    let device = SimulatedDevice::new();
+    device.on_query("*ESR?", |_| b"ERR\n".to_vec());
+    let client = connect(&device);
+
+    let err = client.read_event_status().unwrap_err();
+    assert!(matches!(err.without_context(), Error::InvalidResponse(resp) if resp == "ERR"));
+}
+
+/// Answer `SYST:ERR?` with the given errors, then with `0,"No error"`
+fn error_queue(device: &SimulatedDevice, errors: &[&str]) {
+    let mut errors: Vec<Vec<u8>> = errors
+        .iter()
+        .rev()
+        .map(|e| format!("{}\n", e).into_bytes())
+        .collect();
+    device.on_query("SYST:ERR?", move |_| {
+        errors
+            .pop()
+            .unwrap_or_else(|| b"+0,\"No error\"\n".to_vec())
+    });
+}
+
+#[test]
+fn errors_are_not_checked_by_default() {
+    let device = SimulatedDevice::new();
+    device.set_status_byte(0b0000_0100);
+    error_queue(&device, &["-113,\"Undefined header\""]);
+    let client = connect(&device);
+
+    client.command("FOO").unwrap();
+    assert_eq!(device.messages(), vec![b"FOO".to_vec()]);
+}
+
+#[test]
+fn checked_mode_drains_error_queue() {
+    let device = SimulatedDevice::new();
+    device.set_status_byte(0b0000_0100);
+    error_queue(
+        &device,
+        &["-113,\"Undefined header\"", "-221,\"Settings conflict\""],
+    );
+    let client = connect(&device);
+    client.set_checked(true);
+
+    let err = client.command("FOO").unwrap_err();
+    let errors = err.instrument_errors().unwrap();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].code, -113);
+    assert_eq!(errors[0].message, "Undefined header");
+    assert_eq!(errors[0].command.as_deref(), Some("FOO"));
+    assert_eq!(errors[1].code, -221);
+    assert_eq!(
+        err.to_string(),
+        "instrument reported -113,\"Undefined header\" after \"FOO\"; -221,\"Settings conflict\" after \"FOO\""
+    );
+    assert_eq!(device.messages().len(), 4);
+
+    // the queue is empty once drained
+    client.command("FOO").unwrap();
+    assert!(client.read_errors().unwrap().is_empty());
+}
+
+#[test]
+fn checked_mode_skips_error_queue_without_error() {
+    let device = SimulatedDevice::new();
+    device.on_query("*IDN?", |_| b"SIM,0,0,0\n".to_vec());
+    error_queue(&device, &[]);
+    let client = connect(&device);
+    client.set_checked(true);
+
+    client.command("*RST").unwrap();
+    assert_eq!(client.query("*IDN?").unwrap(), "SIM,0,0,0");
+    assert_eq!(device.messages(), vec![b"*RST".to_vec(), b"*IDN?".to_vec()]);
+}
+
+#[test]
+fn checked_mode_reads_event_status() {
+    let device = SimulatedDevice::new();
+    device.set_status_byte(0b0010_0000);
+    device.on_query("*ESR?", |_| b"+32\n".to_vec());
+    error_queue(&device, &["-100,\"Command error\""]);
+    let client = connect(&device);
+    client.set_checked(true);
+
+    let err = client.command("FOO").unwrap_err();
+    assert!(matches!(
+        err.without_context(),
+        Error::Instrument(errors) if errors[0].code == -100
+    ));
+}